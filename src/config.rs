@@ -1,7 +1,52 @@
 use chrono::Duration;
 pub use cookie::{Key, SameSite};
+use http::{header::CONTENT_TYPE, HeaderMap, StatusCode};
 use std::borrow::Cow;
 
+/// An extra copy of the session's cookies sent under another name with a different SameSite attribute.
+///
+/// Some older browsers reject or mishandle `SameSite=None`, so a common workaround is to send
+/// the cookie with `SameSite=None; Secure` along with a copy that has no SameSite attribute at all.
+/// Whichever copy the browser sends back is accepted.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{CookieVariant, SameSite, SessionConfig};
+///
+/// let config = SessionConfig::default()
+///     .with_cookie_same_site(SameSite::None)
+///     .with_secure(true)
+///     .with_cookie_variant(CookieVariant::new("_legacy"));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct CookieVariant {
+    pub(crate) suffix: Cow<'static, str>,
+    pub(crate) same_site: Option<SameSite>,
+}
+
+impl CookieVariant {
+    /// Constructs a CookieVariant named by adding the suffix to each cookie's name.
+    /// The variant has no SameSite attribute unless one is set.
+    pub fn new(suffix: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            suffix: suffix.into(),
+            same_site: None,
+        }
+    }
+
+    /// Set's the SameSite attribute sent with this variant.
+    #[must_use]
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    pub(crate) fn name(&self, name: &str) -> String {
+        format!("{}{}", name, self.suffix)
+    }
+}
+
 /// Mode at which the Session will function As.
 ///
 /// # Examples
@@ -56,6 +101,18 @@ pub struct SessionConfig {
     pub(crate) cookie_same_site: SameSite,
     /// Session cookie secure flag
     pub(crate) cookie_secure: bool,
+    /// Extra copies of each cookie sent with different SameSite attributes.
+    pub(crate) cookie_variants: Vec<CookieVariant>,
+    /// Response status codes that never have the session's cookies set on them.
+    pub(crate) cookie_skip_statuses: Vec<StatusCode>,
+    /// Response content types that never have the session's cookies set on them.
+    pub(crate) cookie_skip_content_types: Vec<Cow<'static, str>>,
+    /// Name of the JavaScript readable cookie holding the session's CSRF token.
+    /// None disables the CSRF cookie.
+    pub(crate) csrf_cookie_name: Option<Cow<'static, str>>,
+    /// The maximum length a received cookie value can be before it is discarded
+    /// without being decrypted or parsed.
+    pub(crate) cookie_max_value_length: usize,
     /// Disables the need to avoid session saving.
     pub(crate) session_mode: SessionMode,
     /// Sessions the minimal lifespan a session can live in the database before expiring.
@@ -67,16 +124,35 @@ pub struct SessionConfig {
     /// when a users Data has had no changes or is not set to always_save.
     /// This helps alleviate constant Database Updates and widdles it down to a update per Duration per visit.
     pub(crate) expiration_update: Duration,
+    /// The minimum amount a session's last accessed time must move forward before it alone
+    /// causes the session to be saved to the database.
+    pub(crate) last_accessed_resolution: Duration,
     /// Ignore's the update checks and will always save the session to the database if set to true.
     pub(crate) always_save: bool,
+    /// Removes the Key when set is given a value that serializes to null, rather than storing null.
+    pub(crate) null_as_remove: bool,
+    /// Renews the Session ID when Session::mark_privilege_change is called.
+    pub(crate) renew_on_privilege_change: bool,
+    /// Only saves sessions to the database on requests marked with PersistSession.
+    pub(crate) persist_marked_only: bool,
     /// Session Memory lifespan, deturmines when to unload it from memory
     /// this works fine since the data can stay in the database till its needed
     /// if not yet expired.
     pub(crate) memory_lifespan: Duration,
+    /// Sends all of a session's database writes through a single task per session.
+    pub(crate) single_writer: bool,
+    /// Cache-Control value added to responses that set the session's cookies.
+    /// None leaves the response's Cache-Control untouched.
+    pub(crate) cache_control: Option<Cow<'static, str>>,
+    /// Marks sessions as deleted within the database instead of removing them.
+    /// Marked sessions are removed later using SessionStore::purge.
+    pub(crate) soft_delete: bool,
     /// Session Database table name default is async_sessions
     pub(crate) table_name: Cow<'static, str>,
     /// Encyption Key used to encypt cookies for confidentiality, integrity, and authenticity.
     pub(crate) key: Option<Key>,
+    /// Fails SessionStore creation when no Key is set so cookies can not be sent unencrypted by mistake.
+    pub(crate) require_key: bool,
 }
 
 impl std::fmt::Debug for SessionConfig {
@@ -90,12 +166,25 @@ impl std::fmt::Debug for SessionConfig {
             .field("cookie_path", &self.cookie_path)
             .field("cookie_same_site", &self.cookie_same_site)
             .field("cookie_secure", &self.cookie_secure)
+            .field("cookie_variants", &self.cookie_variants)
+            .field("cookie_skip_statuses", &self.cookie_skip_statuses)
+            .field("cookie_skip_content_types", &self.cookie_skip_content_types)
+            .field("csrf_cookie_name", &self.csrf_cookie_name)
+            .field("cookie_max_value_length", &self.cookie_max_value_length)
             .field("session_mode", &self.session_mode)
             .field("lifespan", &self.lifespan)
             .field("max_lifespan", &self.max_lifespan)
             .field("memory_lifespan", &self.memory_lifespan)
+            .field("last_accessed_resolution", &self.last_accessed_resolution)
+            .field("null_as_remove", &self.null_as_remove)
+            .field("persist_marked_only", &self.persist_marked_only)
+            .field("renew_on_privilege_change", &self.renew_on_privilege_change)
+            .field("single_writer", &self.single_writer)
+            .field("cache_control", &self.cache_control)
+            .field("soft_delete", &self.soft_delete)
             .field("table_name", &self.table_name)
             .field("key", &"key hidden")
+            .field("require_key", &self.require_key)
             .finish()
     }
 }
@@ -304,6 +393,26 @@ impl SessionConfig {
         self
     }
 
+    /// Set's how far a session's last accessed time must move forward before it is saved to the database.
+    ///
+    /// The last accessed time is always kept up to date in memory, this only limits how often
+    /// a session that is otherwise unchanged gets rewritten to store it. Changes to the session's data
+    /// are still saved right away and will also save the current last accessed time.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_last_accessed_resolution(Duration::seconds(60));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_last_accessed_resolution(mut self, resolution: Duration) -> Self {
+        self.last_accessed_resolution = resolution;
+        self
+    }
+
     /// This value represents if the database should check for updates to save or
     /// to just save the data regardless of updates. When set to true it will disable the
     /// update checks.
@@ -322,6 +431,216 @@ impl SessionConfig {
         self
     }
 
+    /// Set's whether setting a value that serializes to null removes its Key. Default is false.
+    ///
+    /// With this set `session.set("x", None::<i32>)` behaves like `session.remove("x")`.
+    /// Otherwise the null is stored so `session.get::<Option<i32>>("x")` returns `Some(None)`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_null_as_remove(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_null_as_remove(mut self, null_as_remove: bool) -> Self {
+        self.null_as_remove = null_as_remove;
+        self
+    }
+
+    /// Set's whether Session::mark_privilege_change renews the Session ID. Default is true.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_renew_on_privilege_change(false);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_renew_on_privilege_change(mut self, renew: bool) -> Self {
+        self.renew_on_privilege_change = renew;
+        self
+    }
+
+    /// Set's whether sessions are only saved to the database on marked requests.
+    ///
+    /// When set to true a session is only saved when the response was marked with PersistSession,
+    /// using PersistSessionLayer on the route or by returning it from the handler,
+    /// or when the handler called Session::persist. On other routes changes stay in memory
+    /// until a marked request saves them. Renewed and destroyed sessions are still removed from the database.
+    /// Unmarked requests also never extend the session's expiry within the database, so a session only used
+    /// on unmarked routes expires one lifetime after its last marked request.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_persist_marked_only(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_persist_marked_only(mut self, persist_marked_only: bool) -> Self {
+        self.persist_marked_only = persist_marked_only;
+        self
+    }
+
+    /// Set's whether a session's database writes all go through a single writer task.
+    ///
+    /// When many requests share one session their writes can finish out of order.
+    /// With this set each session gets a task that saves its writes one at a time and
+    /// combines writes that queue up while the database is busy into a single save of the latest data.
+    /// The task shuts down once the session has had no writes for a while.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_single_writer(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_single_writer(mut self, single_writer: bool) -> Self {
+        self.single_writer = single_writer;
+        self
+    }
+
+    /// Adds a variant that each of the session's cookies is also sent as.
+    ///
+    /// When reading cookies the main name is tried first then each variant in the order they were added.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{CookieVariant, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_cookie_variant(CookieVariant::new("_legacy"));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_variant(mut self, variant: CookieVariant) -> Self {
+        self.cookie_variants.push(variant);
+        self
+    }
+
+    /// Adds a response status code that the session's cookies are never set on, such as 304 Not Modified.
+    ///
+    /// The session is still saved to the database, only the Set-Cookie headers are left out.
+    /// If the session was renewed or destroyed during the request the client keeps its old cookie
+    /// and will get a new session on its next request.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use http::StatusCode;
+    ///
+    /// let config = SessionConfig::default().with_cookie_skip_status(StatusCode::NOT_MODIFIED);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_skip_status(mut self, status: StatusCode) -> Self {
+        self.cookie_skip_statuses.push(status);
+        self
+    }
+
+    /// Adds a response content type that the session's cookies are never set on, such as text/event-stream.
+    ///
+    /// Only the media type is compared, ignoring case and any parameters like charset.
+    /// The session is still saved to the database, only the Set-Cookie headers are left out.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_cookie_skip_content_type("text/event-stream");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_skip_content_type(
+        mut self,
+        content_type: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.cookie_skip_content_types.push(content_type.into());
+        self
+    }
+
+    /// Checks if a response's status or content type means the session's cookies should not be set.
+    pub(crate) fn skips_cookies(&self, status: StatusCode, headers: &HeaderMap) -> bool {
+        if self.cookie_skip_statuses.contains(&status) {
+            return true;
+        }
+
+        if let Some(content_type) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
+            let media = content_type.split(';').next().unwrap_or("").trim();
+
+            return self
+                .cookie_skip_content_types
+                .iter()
+                .any(|skip| skip.eq_ignore_ascii_case(media));
+        }
+
+        false
+    }
+
+    /// Set's the name of a cookie that holds the session's CSRF token for double-submit checks.
+    ///
+    /// The token is kept within the session and sent in a cookie that is not HttpOnly or encrypted
+    /// so client side scripts can read it and send it back in a header or form field.
+    /// Use Session::verify_double_submit to compare the sent value against the session's token.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_csrf_cookie("csrf_token");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_csrf_cookie(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.csrf_cookie_name = Some(name.into());
+        self
+    }
+
+    /// Set's the Cache-Control value added to responses that set the session's cookies.
+    ///
+    /// This prevents shared caches like CDNs from storing a response along with someone's session cookie.
+    /// If the response already has a Cache-Control header the value is appended to it unless
+    /// it already contains a private, public or no-store directive.
+    /// An existing value that is not visible ASCII is left unchanged, and an empty one is replaced.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_cache_control("private");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cache_control(mut self, cache_control: impl Into<Cow<'static, str>>) -> Self {
+        self.cache_control = Some(cache_control.into());
+        self
+    }
+
+    /// Set's whether destroyed and expired sessions are only marked as deleted in the database.
+    ///
+    /// When set to true the session's row is kept with a deleted_at timestamp instead of being removed,
+    /// and is no longer loaded. Use SessionStore::purge to remove old marked sessions.
+    /// The pool must have soft delete enabled with its own with_soft_delete as well, as that adds the deleted_at column.
+    /// SessionStore::try_new returns an error when the two disagree, and for pools that can not mark a session like Redis.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_soft_delete(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_soft_delete(mut self, soft_delete: bool) -> Self {
+        self.soft_delete = soft_delete;
+        self
+    }
+
     /// Set's the session's secure flag for if it gets sent over https.
     ///
     /// # Examples
@@ -337,8 +656,29 @@ impl SessionConfig {
         self
     }
 
+    /// Set's the maximum length of a received cookie's value.
+    ///
+    /// Cookies with a value longer than this are discarded and logged before any
+    /// decryption or parsing is attempted. Browsers generally limit a cookie to 4096 bytes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_cookie_max_value_length(1024);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_max_value_length(mut self, length: usize) -> Self {
+        self.cookie_max_value_length = length;
+        self
+    }
+
     /// Set's the session's database table name.
     ///
+    /// The SQL pools only accept names made of ASCII letters, numbers and underscores,
+    /// optionally schema qualified like schema.table, and return a ['SessionError::ConfigError'] otherwise.
+    ///
     /// # Examples
     /// ```rust
     /// use axum_session::SessionConfig;
@@ -372,32 +712,67 @@ impl SessionConfig {
         self.key = Some(key);
         self
     }
+
+    /// Set's whether a Key must be set for private cookies.
+    ///
+    /// When set to true SessionStore::try_new returns a ['SessionError::ConfigError'] if no Key was set,
+    /// and SessionStore::new panics, rather than the sessions cookies being sent unencrypted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{Key, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_key(Key::generate()).with_require_key(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_require_key(mut self, require_key: bool) -> Self {
+        self.require_key = require_key;
+        self
+    }
 }
 
 impl Default for SessionConfig {
     fn default() -> Self {
         Self {
-            /// Set to a 6 hour default in Database Session stores unloading.
+            // Set to a 6 hour default in Database Session stores unloading.
             lifespan: Duration::hours(6),
             cookie_name: "sqlx_session".into(),
             cookie_path: "/".into(),
             cookie_max_age: Some(Duration::days(100)),
             cookie_http_only: true,
             cookie_secure: false,
+            cookie_variants: Vec::new(),
+            // Cookies are set on every response by default.
+            cookie_skip_statuses: Vec::new(),
+            cookie_skip_content_types: Vec::new(),
+            // CSRF cookie is disabled by default.
+            csrf_cookie_name: None,
+            cookie_max_value_length: 4096,
             cookie_domain: None,
             cookie_same_site: SameSite::Lax,
             storable_cookie_name: "session_acceptance".into(),
             table_name: "async_sessions".into(),
-            /// Unload memory after 60 minutes if it has not been accessed.
+            // Unload memory after 60 minutes if it has not been accessed.
             memory_lifespan: Duration::minutes(60),
-            /// Unload long term session after 60 days if it has not been accessed.
+            // Unload long term session after 60 days if it has not been accessed.
             max_lifespan: Duration::days(60),
-            /// Default to update the database every hour if the session is still being requested.
+            // Default to update the database every hour if the session is still being requested.
             expiration_update: Duration::hours(5),
+            // Save an otherwise unchanged session's last accessed time at most once an hour.
+            last_accessed_resolution: Duration::hours(1),
             always_save: false,
+            // Setting None removes the Key so it reads back as missing, like it was never set.
+            null_as_remove: false,
+            renew_on_privilege_change: true,
+            persist_marked_only: false,
+            single_writer: false,
+            cache_control: None,
+            soft_delete: false,
             session_mode: SessionMode::Always,
-            /// Key is set to None so Private cookies are not used by default. Please set this if you want to use private cookies.
+            // Key is set to None so Private cookies are not used by default. Please set this if you want to use private cookies.
             key: None,
+            require_key: false,
         }
     }
 }
@@ -0,0 +1,153 @@
+use crate::SessionError;
+use std::borrow::Cow;
+
+/// The column names the SQL pools use within the session table.
+///
+/// Use this to store sessions within an existing table whose columns are named differently.
+/// Names may only contain ASCII letters, numbers and underscores, can not start with a number
+/// and must fit within the database's identifier limit, 63 bytes for Postgres and 64 for MySql and Sqlite.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{SessionColumns, SessionPgPool};
+///
+/// let columns = SessionColumns::new().with_id("session_id").with_session("payload");
+/// let pool = SessionPgPool::from(pg_pool).with_columns(columns).unwrap();
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct SessionColumns {
+    pub(crate) id: Cow<'static, str>,
+    pub(crate) session: Cow<'static, str>,
+    pub(crate) expires: Cow<'static, str>,
+    pub(crate) deleted_at: Cow<'static, str>,
+}
+
+impl SessionColumns {
+    /// Creates [`Default`] column names of [`SessionColumns`].
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set's the name of the column holding the session's UUID. Default is id.
+    #[must_use]
+    pub fn with_id(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.id = name.into();
+        self
+    }
+
+    /// Set's the name of the column holding the session's data. Default is session.
+    #[must_use]
+    pub fn with_session(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.session = name.into();
+        self
+    }
+
+    /// Set's the name of the column holding the session's expiration time. Default is expires.
+    #[must_use]
+    pub fn with_expires(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.expires = name.into();
+        self
+    }
+
+    /// Set's the name of the column holding when the session was marked as deleted. Default is deleted_at.
+    #[must_use]
+    pub fn with_deleted_at(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.deleted_at = name.into();
+        self
+    }
+
+    /// Checks that every column name is a plain identifier so it is safe to place within a query.
+    pub(crate) fn validate(&self, max_len: usize) -> Result<(), SessionError> {
+        for name in [&self.id, &self.session, &self.expires, &self.deleted_at] {
+            if !is_identifier(name, max_len) {
+                return Err(SessionError::ConfigError(format!(
+                    "invalid column name {:?}",
+                    name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the table name is a plain identifier, or schema.table with each part one,
+    /// so it is safe to place within a query.
+    pub(crate) fn validate_table(table_name: &str, max_len: usize) -> Result<(), SessionError> {
+        if !table_name
+            .split('.')
+            .all(|name| is_identifier(name, max_len))
+            || table_name.split('.').count() > 2
+        {
+            return Err(SessionError::ConfigError(format!(
+                "invalid table name {:?}",
+                table_name
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Fills in the table and quoted column names within a query.
+    ///
+    /// The deleted_at column is only used when soft_delete is set, so tables created without it keep working.
+    /// The table name is checked by validate_table when the pool is initiated. It is left unquoted
+    /// so the database folds its case the same way it did for earlier releases.
+    /// The table is filled in last so a name can never add placeholders of its own.
+    pub(crate) fn apply(
+        &self,
+        query: &str,
+        table_name: &str,
+        quote: char,
+        soft_delete: bool,
+    ) -> String {
+        let (column, and_not_deleted, where_not_deleted, clear_deleted_at) = if soft_delete {
+            (
+                ", %%DELETED_AT%% INTEGER NULL",
+                "AND %%DELETED_AT%% IS NULL",
+                "WHERE %%DELETED_AT%% IS NULL",
+                ", %%DELETED_AT%% = NULL",
+            )
+        } else {
+            ("", "", "", "")
+        };
+
+        query
+            .replace("%%DELETED_AT_COLUMN%%", column)
+            .replace("%%AND_NOT_DELETED%%", and_not_deleted)
+            .replace("%%WHERE_NOT_DELETED%%", where_not_deleted)
+            .replace("%%CLEAR_DELETED_AT%%", clear_deleted_at)
+            .replace("%%ID%%", &format!("{quote}{}{quote}", self.id))
+            .replace("%%SESSION%%", &format!("{quote}{}{quote}", self.session))
+            .replace("%%EXPIRES%%", &format!("{quote}{}{quote}", self.expires))
+            .replace(
+                "%%DELETED_AT%%",
+                &format!("{quote}{}{quote}", self.deleted_at),
+            )
+            .replace("%%TABLE_NAME%%", table_name)
+    }
+}
+
+/// Checks that a name only contains ASCII letters, numbers and underscores, does not start with a number
+/// and is at most max_len bytes long.
+fn is_identifier(name: &str, max_len: usize) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.len() <= max_len
+}
+
+impl Default for SessionColumns {
+    fn default() -> Self {
+        Self {
+            id: "id".into(),
+            session: "session".into(),
+            expires: "expires".into(),
+            deleted_at: "deleted_at".into(),
+        }
+    }
+}
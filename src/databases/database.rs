@@ -1,5 +1,6 @@
 use crate::SessionError;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 /// The Trait used to identify a database pool.
 /// This can be freely implemented but default implementations for the supported database types are already included
@@ -31,6 +32,21 @@ pub trait DatabasePool {
     /// if an error occurs it should be propagated to the caller.
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError>;
 
+    /// This a called to receive only the session's expiration time from the database using the given table name.
+    /// Returns None if the session does not exist or has no expiration time.
+    /// By default this returns SessionError::GenericNotSupportedError.
+    /// if an error occurs it should be propagated to the caller.
+    async fn expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<DateTime<Utc>>, SessionError> {
+        let _ = (id, table_name);
+        Err(SessionError::GenericNotSupportedError(
+            "expiry is not supported by this database pool".to_owned(),
+        ))
+    }
+
     /// This a called to delete one session from the database using the given table name.
     /// if an error occurs it should be propagated to the caller.
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError>;
@@ -43,6 +59,38 @@ pub trait DatabasePool {
     /// if an error occurs it should be propagated to the caller.
     async fn delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError>;
 
+    /// This a called to mark one session as deleted in the database using the given table name.
+    /// The session is kept but must no longer be returned by load, exists or count.
+    /// By default this deletes the session with delete_one_by_id.
+    /// if an error occurs it should be propagated to the caller.
+    async fn soft_delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        self.delete_one_by_id(id, table_name).await
+    }
+
+    /// This a called to mark all sessions that expired as deleted in the database using the given table name.
+    /// By default this deletes them with delete_by_expiry.
+    /// if an error occurs it should be propagated to the caller.
+    async fn soft_delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        self.delete_by_expiry(table_name).await
+    }
+
+    /// This a called to remove all sessions marked as deleted before the given time from the database
+    /// using the given table name.
+    /// before is a unix timestamp(number of non-leap seconds since January 1, 1970 0:00:00 UTC).
+    /// By default nothing is ever marked as deleted so this does nothing.
+    /// if an error occurs it should be propagated to the caller.
+    async fn purge_deleted(&self, before: i64, table_name: &str) -> Result<(), SessionError> {
+        let _ = (before, table_name);
+        Ok(())
+    }
+
+    /// This a called to check whether the pool marks sessions as deleted instead of removing them.
+    /// SessionStore::try_new returns an error if this does not match SessionConfig::with_soft_delete.
+    /// By default this returns false, as the default soft delete functions remove sessions.
+    fn soft_delete(&self) -> bool {
+        false
+    }
+
     /// This a called to delete all sessions from the database using the given table name.
     /// if an error occurs it should be propagated to the caller.
     async fn delete_all(&self, table_name: &str) -> Result<(), SessionError>;
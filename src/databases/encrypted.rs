@@ -0,0 +1,219 @@
+use crate::{DatabasePool, Session, SessionError, SessionStore};
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Arc};
+
+pub type SessionEncryptedSession<P> = Session<SessionEncryptedPool<P>>;
+pub type SessionEncryptedSessionStore<P> = SessionStore<SessionEncryptedPool<P>>;
+
+/// The Trait used to supply data keys for encrypting sessions at rest.
+///
+/// This is generally implemented on top of a key management service where a master key
+/// wraps each data key so only the wrapped form is ever stored alongside the session.
+/// Data keys must be 32 bytes as sessions are encrypted using AES-256-GCM.
+/// Both functions are called for every save and load, so implementations should cache keys where allowed.
+#[async_trait]
+pub trait KeyProvider {
+    /// This is called to get a data key to encrypt a session with.
+    /// Returns the id of the master key used, the plaintext data key and the wrapped data key.
+    /// if an error occurs it should be propagated to the caller.
+    async fn data_key(&self) -> Result<(String, Vec<u8>, Vec<u8>), SessionError>;
+
+    /// This is called to get the plaintext data key back from a wrapped data key
+    /// using the master key with the given id.
+    /// if an error occurs it should be propagated to the caller.
+    async fn unwrap(&self, key_id: &str, wrapped_key: &[u8]) -> Result<Vec<u8>, SessionError>;
+}
+
+/// What gets stored in place of the session, all binary values are base64 encoded.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    key_id: String,
+    wrapped_key: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Encrypted Pool type for DatabasePool.
+/// Encrypts sessions using data keys from a KeyProvider before storing them in the inner pool.
+///
+/// Each session is bound to its Session ID, so a stored session copied to another ID fails to decrypt.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{SessionEncryptedPool, SessionPgPool, SessionStore, SessionConfig};
+///
+/// let pool = SessionEncryptedPool::new(SessionPgPool::from(pg_pool), my_kms_provider);
+/// let session_store = SessionStore::new(Some(pool), SessionConfig::default());
+/// ```
+///
+pub struct SessionEncryptedPool<P> {
+    pool: P,
+    provider: Arc<dyn KeyProvider + Send + Sync>,
+}
+
+impl<P> SessionEncryptedPool<P> {
+    /// Constructs a SessionEncryptedPool storing its sessions within the given pool.
+    pub fn new(pool: P, provider: impl KeyProvider + Send + Sync + 'static) -> Self {
+        Self {
+            pool,
+            provider: Arc::new(provider),
+        }
+    }
+
+    async fn encrypt(&self, id: &str, session: &str) -> Result<String, SessionError> {
+        let (key_id, key, wrapped_key) = self.provider.data_key().await?;
+        let cipher = cipher(&key)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: session.as_bytes(),
+                    aad: id.as_bytes(),
+                },
+            )
+            .map_err(|_| SessionError::EncryptionError("failed to encrypt session".to_owned()))?;
+
+        Ok(serde_json::to_string(&Envelope {
+            key_id,
+            wrapped_key: STANDARD.encode(wrapped_key),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        })?)
+    }
+
+    async fn decrypt(&self, id: &str, session: &str) -> Result<String, SessionError> {
+        let envelope: Envelope = serde_json::from_str(session)?;
+        let wrapped_key = decode(&envelope.wrapped_key)?;
+        let nonce = decode(&envelope.nonce)?;
+        let ciphertext = decode(&envelope.ciphertext)?;
+
+        if nonce.len() != 12 {
+            return Err(SessionError::EncryptionError(
+                "session nonce has the wrong length".to_owned(),
+            ));
+        }
+
+        let key = self.provider.unwrap(&envelope.key_id, &wrapped_key).await?;
+        let plaintext = cipher(&key)?
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: id.as_bytes(),
+                },
+            )
+            .map_err(|_| SessionError::EncryptionError("failed to decrypt session".to_owned()))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|_| SessionError::EncryptionError("session is not valid utf8".to_owned()))
+    }
+}
+
+fn cipher(key: &[u8]) -> Result<Aes256Gcm, SessionError> {
+    Aes256Gcm::new_from_slice(key)
+        .map_err(|_| SessionError::EncryptionError("data key must be 32 bytes".to_owned()))
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, SessionError> {
+    STANDARD
+        .decode(value)
+        .map_err(|_| SessionError::EncryptionError("session is not valid base64".to_owned()))
+}
+
+impl<P: Clone> Clone for SessionEncryptedPool<P> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            provider: self.provider.clone(),
+        }
+    }
+}
+
+impl<P: fmt::Debug> fmt::Debug for SessionEncryptedPool<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionEncryptedPool")
+            .field("pool", &self.pool)
+            .field("provider", &"provider hidden")
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<P> DatabasePool for SessionEncryptedPool<P>
+where
+    P: DatabasePool + Send + Sync,
+{
+    async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
+        self.pool.initiate(table_name).await
+    }
+
+    async fn delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        self.pool.delete_by_expiry(table_name).await
+    }
+
+    async fn soft_delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        self.pool.soft_delete_by_expiry(table_name).await
+    }
+
+    async fn purge_deleted(&self, before: i64, table_name: &str) -> Result<(), SessionError> {
+        self.pool.purge_deleted(before, table_name).await
+    }
+
+    async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
+        self.pool.count(table_name).await
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        session: &str,
+        expires: i64,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        let session = self.encrypt(id, session).await?;
+        self.pool.store(id, &session, expires, table_name).await
+    }
+
+    async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        match self.pool.load(id, table_name).await? {
+            Some(session) => Ok(Some(self.decrypt(id, &session).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<DateTime<Utc>>, SessionError> {
+        self.pool.expiry(id, table_name).await
+    }
+
+    async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        self.pool.delete_one_by_id(id, table_name).await
+    }
+
+    async fn soft_delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        self.pool.soft_delete_one_by_id(id, table_name).await
+    }
+
+    async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
+        self.pool.exists(id, table_name).await
+    }
+
+    fn soft_delete(&self) -> bool {
+        self.pool.soft_delete()
+    }
+
+    async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
+        self.pool.delete_all(table_name).await
+    }
+}
@@ -0,0 +1,367 @@
+use crate::{DatabasePool, Session, SessionError, SessionStore};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use tokio::{fs, io::AsyncWriteExt};
+use uuid::Uuid;
+
+pub type SessionFileSession = Session<SessionFilePool>;
+pub type SessionFileSessionStore = SessionStore<SessionFilePool>;
+
+/// What is written to each session's file.
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    expires: i64,
+    deleted_at: Option<i64>,
+    session: String,
+}
+
+impl SessionFile {
+    fn is_live(&self, now: i64) -> bool {
+        self.expires > now && self.deleted_at.is_none()
+    }
+}
+
+/// File Pool type for DatabasePool.
+/// Use this for small single server apps that want sessions to last across restarts without a database.
+///
+/// Each session is stored as a JSON file named by its UUID within a folder named after the table,
+/// under the given directory. Files are written and synced to a temporary file first, then renamed
+/// into place and the folder synced, so a crash never leaves a partly written session.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{SessionFilePool, SessionStore, SessionConfig};
+///
+/// let pool = SessionFilePool::new("./sessions");
+/// let session_store = SessionStore::new(Some(pool), SessionConfig::default());
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct SessionFilePool {
+    dir: PathBuf,
+    soft_delete: bool,
+}
+
+impl SessionFilePool {
+    /// Constructs a SessionFilePool storing sessions under the given directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            soft_delete: false,
+        }
+    }
+
+    /// Set's whether sessions are marked as deleted within their file instead of the file being removed.
+    /// Default is false.
+    ///
+    /// This must match SessionConfig::with_soft_delete or SessionStore::try_new returns an error.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::SessionFilePool;
+    ///
+    /// let pool = SessionFilePool::new("./sessions").with_soft_delete(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_soft_delete(mut self, soft_delete: bool) -> Self {
+        self.soft_delete = soft_delete;
+        self
+    }
+
+    fn table_dir(&self, table_name: &str) -> Result<PathBuf, SessionError> {
+        let valid = !table_name.is_empty()
+            && table_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+        if !valid {
+            return Err(SessionError::ConfigError(format!(
+                "invalid table name {:?} for the file pool",
+                table_name
+            )));
+        }
+
+        Ok(self.dir.join(table_name))
+    }
+
+    /// Only UUIDs are used as file names so an ID can never point outside of the table's folder.
+    fn path(&self, id: &str, table_name: &str) -> Result<Option<PathBuf>, SessionError> {
+        let dir = self.table_dir(table_name)?;
+
+        Ok(Uuid::parse_str(id)
+            .ok()
+            .map(|id| dir.join(format!("{}.json", id))))
+    }
+
+    async fn read(path: &Path) -> Result<Option<SessionFile>, SessionError> {
+        match fs::read(path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn write(path: &Path, file: &SessionFile) -> Result<(), SessionError> {
+        let tmp = path.with_extension(format!("{}.tmp", Uuid::new_v4()));
+        let bytes = serde_json::to_vec(file)?;
+
+        let written = async {
+            let mut file = fs::File::create(&tmp).await?;
+            file.write_all(&bytes).await?;
+            file.sync_all().await?;
+            fs::rename(&tmp, path).await
+        }
+        .await;
+
+        if let Err(err) = written {
+            let _ = fs::remove_file(&tmp).await;
+            return Err(err.into());
+        }
+
+        // The rename only survives a crash once the folder holding it is synced.
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            fs::File::open(dir).await?.sync_all().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn remove(path: &Path) -> Result<(), SessionError> {
+        match fs::remove_file(path).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Lists every session file within the table's folder, skipping temporary files.
+    async fn files(&self, table_name: &str) -> Result<Vec<PathBuf>, SessionError> {
+        let mut entries = match fs::read_dir(self.table_dir(table_name)?).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut files = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+
+            if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Runs the given check on every session file, removing or rewriting them as it returns.
+    async fn sweep<F>(&self, table_name: &str, mut check: F) -> Result<(), SessionError>
+    where
+        F: FnMut(&mut SessionFile) -> Sweep + Send,
+    {
+        for path in self.files(table_name).await? {
+            // Ignore files removed while we were scanning and ones we can not read.
+            let mut file = match Self::read(&path).await {
+                Ok(Some(file)) => file,
+                _ => continue,
+            };
+
+            match check(&mut file) {
+                Sweep::Keep => {}
+                Sweep::Write => Self::write(&path, &file).await?,
+                Sweep::Remove => Self::remove(&path).await?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+enum Sweep {
+    Keep,
+    Write,
+    Remove,
+}
+
+#[async_trait]
+impl DatabasePool for SessionFilePool {
+    async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
+        fs::create_dir_all(self.table_dir(table_name)?).await?;
+        Ok(())
+    }
+
+    async fn delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        let now = Utc::now().timestamp();
+
+        self.sweep(table_name, |file| {
+            if file.expires < now {
+                Sweep::Remove
+            } else {
+                Sweep::Keep
+            }
+        })
+        .await
+    }
+
+    async fn soft_delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return self.delete_by_expiry(table_name).await;
+        }
+
+        let now = Utc::now().timestamp();
+
+        self.sweep(table_name, |file| {
+            if file.expires < now && file.deleted_at.is_none() {
+                file.deleted_at = Some(now);
+                Sweep::Write
+            } else {
+                Sweep::Keep
+            }
+        })
+        .await
+    }
+
+    async fn purge_deleted(&self, before: i64, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return Ok(());
+        }
+
+        self.sweep(table_name, |file| {
+            if file
+                .deleted_at
+                .is_some_and(|deleted_at| deleted_at < before)
+            {
+                Sweep::Remove
+            } else {
+                Sweep::Keep
+            }
+        })
+        .await
+    }
+
+    async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
+        let mut count = 0;
+        let now = Utc::now().timestamp();
+
+        for path in self.files(table_name).await? {
+            if let Ok(Some(file)) = Self::read(&path).await {
+                if file.is_live(now) {
+                    count += 1;
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        session: &str,
+        expires: i64,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        let path = self.path(id, table_name)?.ok_or_else(|| {
+            SessionError::GenericInsertError(format!("{} is not a valid session id", id))
+        })?;
+
+        let file = SessionFile {
+            expires,
+            deleted_at: None,
+            session: session.to_owned(),
+        };
+
+        Self::write(&path, &file).await
+    }
+
+    async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        let path = match self.path(id, table_name)? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let now = Utc::now().timestamp();
+
+        Ok(Self::read(&path)
+            .await?
+            .filter(|file| file.is_live(now))
+            .map(|file| file.session))
+    }
+
+    async fn expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<DateTime<Utc>>, SessionError> {
+        let path = match self.path(id, table_name)? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let now = Utc::now().timestamp();
+
+        Ok(Self::read(&path)
+            .await?
+            .filter(|file| file.is_live(now))
+            .and_then(|file| Utc.timestamp_opt(file.expires, 0).single()))
+    }
+
+    async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        match self.path(id, table_name)? {
+            Some(path) => Self::remove(&path).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn soft_delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return self.delete_one_by_id(id, table_name).await;
+        }
+
+        let path = match self.path(id, table_name)? {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(mut file) = Self::read(&path).await? {
+            if file.deleted_at.is_none() {
+                file.deleted_at = Some(Utc::now().timestamp());
+                Self::write(&path, &file).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
+        let path = match self.path(id, table_name)? {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+
+        let now = Utc::now().timestamp();
+
+        Ok(Self::read(&path)
+            .await?
+            .is_some_and(|file| file.is_live(now)))
+    }
+
+    fn soft_delete(&self) -> bool {
+        self.soft_delete
+    }
+
+    async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
+        for path in self.files(table_name).await? {
+            Self::remove(&path).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,22 @@
+#[cfg(any(
+    feature = "mysql-rustls",
+    feature = "mysql-native",
+    feature = "postgres-rustls",
+    feature = "postgres-native",
+    feature = "sqlite-rustls",
+    feature = "sqlite-native"
+))]
+mod columns;
+#[cfg(any(
+    feature = "mysql-rustls",
+    feature = "mysql-native",
+    feature = "postgres-rustls",
+    feature = "postgres-native",
+    feature = "sqlite-rustls",
+    feature = "sqlite-native"
+))]
+pub use columns::*;
+
 #[cfg(any(feature = "mysql-rustls", feature = "mysql-native"))]
 mod mysql;
 #[cfg(any(feature = "mysql-rustls", feature = "mysql-native"))]
@@ -24,7 +43,13 @@ mod surrealdb;
 pub use surrealdb::*;
 
 mod database;
+mod encrypted;
+mod file;
 mod null;
+mod routed;
 
 pub use database::DatabasePool;
+pub use encrypted::*;
+pub use file::*;
 pub use null::*;
+pub use routed::*;
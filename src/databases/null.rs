@@ -1,5 +1,6 @@
 use crate::{DatabasePool, Session, SessionError, SessionStore};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 pub type SessionNullSession = Session<SessionNullPool>;
 pub type SessionNullSessionStore = SessionStore<SessionNullPool>;
@@ -37,6 +38,14 @@ impl DatabasePool for SessionNullPool {
         Ok(None)
     }
 
+    async fn expiry(
+        &self,
+        _id: &str,
+        _table_name: &str,
+    ) -> Result<Option<DateTime<Utc>>, SessionError> {
+        Ok(None)
+    }
+
     async fn delete_one_by_id(&self, _id: &str, _table_name: &str) -> Result<(), SessionError> {
         Ok(())
     }
@@ -45,6 +54,22 @@ impl DatabasePool for SessionNullPool {
         Ok(false)
     }
 
+    async fn soft_delete_one_by_id(
+        &self,
+        _id: &str,
+        _table_name: &str,
+    ) -> Result<(), SessionError> {
+        Ok(())
+    }
+
+    async fn soft_delete_by_expiry(&self, _table_name: &str) -> Result<(), SessionError> {
+        Ok(())
+    }
+
+    async fn purge_deleted(&self, _before: i64, _table_name: &str) -> Result<(), SessionError> {
+        Ok(())
+    }
+
     async fn delete_all(&self, _table_name: &str) -> Result<(), SessionError> {
         Ok(())
     }
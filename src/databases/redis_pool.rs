@@ -1,5 +1,6 @@
 use crate::{DatabasePool, Session, SessionError, SessionStore};
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
 use redis::Client;
 
 pub type SessionRedisSession = Session<SessionRedisPool>;
@@ -56,8 +57,24 @@ impl DatabasePool for SessionRedisPool {
 
     async fn load(&self, id: &str, _table_name: &str) -> Result<Option<String>, SessionError> {
         let mut con = self.client.get_async_connection().await?;
-        let result: String = redis::cmd("GET").arg(id).query_async(&mut con).await?;
-        Ok(Some(result))
+        let result: Option<String> = redis::cmd("GET").arg(id).query_async(&mut con).await?;
+        Ok(result)
+    }
+
+    async fn expiry(
+        &self,
+        id: &str,
+        _table_name: &str,
+    ) -> Result<Option<DateTime<Utc>>, SessionError> {
+        let mut con = self.client.get_async_connection().await?;
+        let ttl: i64 = redis::cmd("TTL").arg(id).query_async(&mut con).await?;
+
+        // TTL is negative when the key is missing or has no expiration.
+        if ttl < 0 {
+            Ok(None)
+        } else {
+            Ok(Some(Utc::now() + Duration::seconds(ttl)))
+        }
     }
 
     async fn delete_one_by_id(&self, id: &str, _table_name: &str) -> Result<(), SessionError> {
@@ -73,6 +90,21 @@ impl DatabasePool for SessionRedisPool {
         Ok(exists)
     }
 
+    async fn soft_delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        // Redis has no columns to mark a session with, so it is removed outright.
+        self.delete_one_by_id(id, table_name).await
+    }
+
+    async fn soft_delete_by_expiry(&self, _table_name: &str) -> Result<(), SessionError> {
+        // Redis does this for use using the Expiry Options.
+        Ok(())
+    }
+
+    async fn purge_deleted(&self, _before: i64, _table_name: &str) -> Result<(), SessionError> {
+        // Nothing is ever marked as deleted within Redis.
+        Ok(())
+    }
+
     async fn delete_all(&self, _table_name: &str) -> Result<(), SessionError> {
         let mut con = self.client.get_async_connection().await?;
         redis::cmd("FLUSHDB").query_async(&mut con).await?;
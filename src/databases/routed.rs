@@ -0,0 +1,226 @@
+use crate::{DatabasePool, Session, SessionError, SessionStore};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{borrow::Cow, fmt, sync::Arc};
+
+pub type SessionRoutedSession = Session<SessionRoutedPool>;
+pub type SessionRoutedSessionStore = SessionStore<SessionRoutedPool>;
+
+/// The name a pool is registered under within a SessionRoutedPool.
+pub type BackendId = Cow<'static, str>;
+
+type RoutedPool = Arc<dyn DatabasePool + Send + Sync>;
+type Router = Arc<dyn Fn(&str) -> BackendId + Send + Sync>;
+
+/// Routed Pool type for DatabasePool.
+/// Use this when sessions should be split between several databases.
+///
+/// Each session is stored within the pool the routing function picks for its Session ID.
+/// As the ID never changes a session always stays within one pool, so the session's data is never read
+/// to route it and each save is a single write. This also means it works within a SessionEncryptedPool.
+///
+/// Loading and checking a session tries its routed pool first and then the others in the order they were added,
+/// so sessions stored before the routing function changed are still found. Their old copy is removed
+/// when the session is destroyed or expires.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{SessionRoutedPool, SessionPgPool, SessionRedisPool};
+///
+/// let pool = SessionRoutedPool::new(|id| {
+///     if id.starts_with(['0', '1', '2', '3']) {
+///         "redis".into()
+///     } else {
+///         "postgres".into()
+///     }
+/// })
+/// .with_pool("redis", SessionRedisPool::from(redis_client))
+/// .with_pool("postgres", SessionPgPool::from(pg_pool));
+/// ```
+///
+#[derive(Clone)]
+pub struct SessionRoutedPool {
+    pools: Vec<(BackendId, RoutedPool)>,
+    router: Router,
+}
+
+impl SessionRoutedPool {
+    /// Constructs a SessionRoutedPool with no pools using the given routing function,
+    /// which is given each session's ID.
+    pub fn new(router: impl Fn(&str) -> BackendId + Send + Sync + 'static) -> Self {
+        Self {
+            pools: Vec::new(),
+            router: Arc::new(router),
+        }
+    }
+
+    /// Registers a pool that sessions can be routed to by the given name.
+    #[must_use]
+    pub fn with_pool(
+        mut self,
+        id: impl Into<BackendId>,
+        pool: impl DatabasePool + Send + Sync + 'static,
+    ) -> Self {
+        self.pools.push((id.into(), Arc::new(pool)));
+        self
+    }
+
+    /// Gets the pool the session with the given ID is routed to.
+    fn route(&self, id: &str) -> Result<&RoutedPool, SessionError> {
+        let backend = (self.router)(id);
+
+        self.pools
+            .iter()
+            .find(|(pool_id, _)| *pool_id == backend)
+            .map(|(_, pool)| pool)
+            .ok_or_else(|| {
+                SessionError::GenericInsertError(format!("No pool is registered as {}", backend))
+            })
+    }
+
+    /// Lists the session's routed pool first followed by the others.
+    fn lookup(&self, id: &str) -> Result<Vec<&RoutedPool>, SessionError> {
+        let routed = self.route(id)?;
+        let mut pools = vec![routed];
+
+        pools.extend(
+            self.pools
+                .iter()
+                .map(|(_, pool)| pool)
+                .filter(|pool| !Arc::ptr_eq(pool, routed)),
+        );
+
+        Ok(pools)
+    }
+}
+
+impl fmt::Debug for SessionRoutedPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionRoutedPool")
+            .field(
+                "pools",
+                &self.pools.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[async_trait]
+impl DatabasePool for SessionRoutedPool {
+    async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
+        for (_, pool) in &self.pools {
+            pool.initiate(table_name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        for (_, pool) in &self.pools {
+            pool.delete_by_expiry(table_name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn soft_delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        for (_, pool) in &self.pools {
+            pool.soft_delete_by_expiry(table_name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn purge_deleted(&self, before: i64, table_name: &str) -> Result<(), SessionError> {
+        for (_, pool) in &self.pools {
+            pool.purge_deleted(before, table_name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
+        let mut count = 0;
+
+        for (_, pool) in &self.pools {
+            count += pool.count(table_name).await?;
+        }
+
+        Ok(count)
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        session: &str,
+        expires: i64,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        self.route(id)?
+            .store(id, session, expires, table_name)
+            .await
+    }
+
+    async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        for pool in self.lookup(id)? {
+            if let Some(session) = pool.load(id, table_name).await? {
+                return Ok(Some(session));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<DateTime<Utc>>, SessionError> {
+        for pool in self.lookup(id)? {
+            if let Some(expires) = pool.expiry(id, table_name).await? {
+                return Ok(Some(expires));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        for (_, pool) in &self.pools {
+            pool.delete_one_by_id(id, table_name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn soft_delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        for (_, pool) in &self.pools {
+            pool.soft_delete_one_by_id(id, table_name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
+        for pool in self.lookup(id)? {
+            if pool.exists(id, table_name).await? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Only true when every pool marks sessions as deleted, so none of them quietly removes sessions.
+    fn soft_delete(&self) -> bool {
+        !self.pools.is_empty() && self.pools.iter().all(|(_, pool)| pool.soft_delete())
+    }
+
+    async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
+        for (_, pool) in &self.pools {
+            pool.delete_all(table_name).await?;
+        }
+
+        Ok(())
+    }
+}
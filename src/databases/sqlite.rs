@@ -1,36 +1,90 @@
-use crate::{DatabasePool, Session, SessionError, SessionStore};
+use crate::{DatabasePool, Session, SessionColumns, SessionError, SessionStore};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, TimeZone, Utc};
 use sqlx::{pool::Pool, Sqlite};
 
 pub type SessionSqliteSession = Session<SessionSqlitePool>;
 pub type SessionSqliteSessionStore = SessionStore<SessionSqlitePool>;
 
+/// The longest table or column name Sqlite allows, in bytes.
+const MAX_IDENTIFIER_LEN: usize = 64;
+
 ///Mysql's Pool type for DatabasePool
 #[derive(Debug, Clone)]
 pub struct SessionSqlitePool {
     pool: Pool<Sqlite>,
+    columns: SessionColumns,
+    soft_delete: bool,
 }
 
 impl From<Pool<Sqlite>> for SessionSqlitePool {
     fn from(conn: Pool<Sqlite>) -> Self {
-        SessionSqlitePool { pool: conn }
+        SessionSqlitePool {
+            pool: conn,
+            columns: SessionColumns::default(),
+            soft_delete: false,
+        }
+    }
+}
+
+impl SessionSqlitePool {
+    /// Set's the column names used within the session table.
+    /// Returns an error if any of the names are not a valid identifier.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionColumns, SessionSqlitePool};
+    ///
+    /// let pool = SessionSqlitePool::from(pool)
+    ///     .with_columns(SessionColumns::new().with_id("session_id"))
+    ///     .unwrap();
+    /// ```
+    ///
+    pub fn with_columns(mut self, columns: SessionColumns) -> Result<Self, SessionError> {
+        columns.validate(MAX_IDENTIFIER_LEN)?;
+        self.columns = columns;
+        Ok(self)
+    }
+
+    /// Set's whether sessions are marked as deleted with a deleted_at column instead of being removed.
+    /// Default is false, which keeps the table in the layout created by earlier releases.
+    ///
+    /// This must match SessionConfig::with_soft_delete or SessionStore::try_new returns an error.
+    /// Tables created before this was enabled need the column added, as initiate will not alter them.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionSqlitePool};
+    ///
+    /// let pool = SessionSqlitePool::from(pool).with_soft_delete(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_soft_delete(mut self, soft_delete: bool) -> Self {
+        self.soft_delete = soft_delete;
+        self
+    }
+
+    fn query(&self, query: &str, table_name: &str) -> String {
+        self.columns.apply(query, table_name, '"', self.soft_delete)
     }
 }
 
 #[async_trait]
 impl DatabasePool for SessionSqlitePool {
     async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
-        sqlx::query(
-            &r#"
+        SessionColumns::validate_table(table_name, MAX_IDENTIFIER_LEN)?;
+
+        sqlx::query(&self.query(
+            r#"
             CREATE TABLE IF NOT EXISTS %%TABLE_NAME%% (
-                "id" VARCHAR(128) NOT NULL PRIMARY KEY,
-                "expires" INTEGER NULL,
-                "session" TEXT NOT NULL
+                %%ID%% VARCHAR(128) NOT NULL PRIMARY KEY,
+                %%EXPIRES%% INTEGER NULL,
+                %%SESSION%% TEXT NOT NULL%%DELETED_AT_COLUMN%%
             )
-        "#
-            .replace("%%TABLE_NAME%%", table_name),
-        )
+        "#,
+            table_name,
+        ))
         .execute(&self.pool)
         .await?;
 
@@ -38,20 +92,54 @@ impl DatabasePool for SessionSqlitePool {
     }
 
     async fn delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
-        sqlx::query(
-            &r#"DELETE FROM %%TABLE_NAME%% WHERE expires < $1"#
-                .replace("%%TABLE_NAME%%", table_name),
-        )
+        sqlx::query(&self.query(
+            r#"DELETE FROM %%TABLE_NAME%% WHERE %%EXPIRES%% < $1"#,
+            table_name,
+        ))
         .bind(Utc::now().timestamp())
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
+    async fn soft_delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return self.delete_by_expiry(table_name).await;
+        }
+
+        sqlx::query(&self.query(
+            r#"
+            UPDATE %%TABLE_NAME%% SET %%DELETED_AT%% = $1
+            WHERE %%EXPIRES%% < $1 AND %%DELETED_AT%% IS NULL
+        "#,
+            table_name,
+        ))
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn purge_deleted(&self, before: i64, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return Ok(());
+        }
+
+        sqlx::query(&self.query(
+            r#"DELETE FROM %%TABLE_NAME%% WHERE %%DELETED_AT%% < $1"#,
+            table_name,
+        ))
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
-        let (count,) = sqlx::query_as(
-            &r#"SELECT COUNT(*) FROM %%TABLE_NAME%%"#.replace("%%TABLE_NAME%%", table_name),
-        )
+        let (count,) = sqlx::query_as(&self.query(
+            r#"SELECT COUNT(*) FROM %%TABLE_NAME%% %%WHERE_NOT_DELETED%%"#,
+            table_name,
+        ))
         .fetch_one(&self.pool)
         .await?;
 
@@ -65,16 +153,16 @@ impl DatabasePool for SessionSqlitePool {
         expires: i64,
         table_name: &str,
     ) -> Result<(), SessionError> {
-        sqlx::query(
-            &r#"
+        sqlx::query(&self.query(
+            r#"
         INSERT INTO %%TABLE_NAME%%
-            (id, session, expires) SELECT $1, $2, $3
-        ON CONFLICT(id) DO UPDATE SET
-            expires = EXCLUDED.expires,
-            session = EXCLUDED.session
-    "#
-            .replace("%%TABLE_NAME%%", table_name),
-        )
+            (%%ID%%, %%SESSION%%, %%EXPIRES%%) SELECT $1, $2, $3
+        ON CONFLICT(%%ID%%) DO UPDATE SET
+            %%EXPIRES%% = EXCLUDED.%%EXPIRES%%,
+            %%SESSION%% = EXCLUDED.%%SESSION%%%%CLEAR_DELETED_AT%%
+    "#,
+            table_name,
+        ))
         .bind(id)
         .bind(session)
         .bind(expires)
@@ -84,13 +172,13 @@ impl DatabasePool for SessionSqlitePool {
     }
 
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
-        let result: Option<(String,)> = sqlx::query_as(
-            &r#"
-            SELECT session FROM %%TABLE_NAME%%
-            WHERE id = $1 AND (expires IS NULL OR expires > $2)
-        "#
-            .replace("%%TABLE_NAME%%", table_name),
-        )
+        let result: Option<(String,)> = sqlx::query_as(&self.query(
+            r#"
+            SELECT %%SESSION%% FROM %%TABLE_NAME%%
+            WHERE %%ID%% = $1 AND (%%EXPIRES%% IS NULL OR %%EXPIRES%% > $2) %%AND_NOT_DELETED%%
+        "#,
+            table_name,
+        ))
         .bind(id)
         .bind(Utc::now().timestamp())
         .fetch_optional(&self.pool)
@@ -99,10 +187,52 @@ impl DatabasePool for SessionSqlitePool {
         Ok(result.map(|(session,)| session))
     }
 
+    async fn expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<DateTime<Utc>>, SessionError> {
+        let result: Option<(Option<i64>,)> = sqlx::query_as(&self.query(
+            r#"
+            SELECT %%EXPIRES%% FROM %%TABLE_NAME%%
+            WHERE %%ID%% = $1 AND (%%EXPIRES%% IS NULL OR %%EXPIRES%% > $2) %%AND_NOT_DELETED%%
+        "#,
+            table_name,
+        ))
+        .bind(id)
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result
+            .and_then(|(expires,)| expires)
+            .and_then(|expires| Utc.timestamp_opt(expires, 0).single()))
+    }
+
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
-        sqlx::query(
-            &r#"DELETE FROM %%TABLE_NAME%% WHERE id = $1"#.replace("%%TABLE_NAME%%", table_name),
-        )
+        sqlx::query(&self.query(
+            r#"DELETE FROM %%TABLE_NAME%% WHERE %%ID%% = $1"#,
+            table_name,
+        ))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn soft_delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return self.delete_one_by_id(id, table_name).await;
+        }
+
+        sqlx::query(&self.query(
+            r#"
+            UPDATE %%TABLE_NAME%% SET %%DELETED_AT%% = $1
+            WHERE %%ID%% = $2 AND %%DELETED_AT%% IS NULL
+        "#,
+            table_name,
+        ))
+        .bind(Utc::now().timestamp())
         .bind(id)
         .execute(&self.pool)
         .await?;
@@ -110,13 +240,13 @@ impl DatabasePool for SessionSqlitePool {
     }
 
     async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
-        let result: Option<(i64,)> = sqlx::query_as(
-            &r#"
+        let result: Option<(i64,)> = sqlx::query_as(&self.query(
+            r#"
             SELECT COUNT(*) FROM %%TABLE_NAME%%
-            WHERE id = $1 AND (expires IS NULL OR expires > $2)
-        "#
-            .replace("%%TABLE_NAME%%", table_name),
-        )
+            WHERE %%ID%% = $1 AND (%%EXPIRES%% IS NULL OR %%EXPIRES%% > $2) %%AND_NOT_DELETED%%
+        "#,
+            table_name,
+        ))
         .bind(id)
         .bind(Utc::now().timestamp())
         .fetch_optional(&self.pool)
@@ -125,8 +255,12 @@ impl DatabasePool for SessionSqlitePool {
         Ok(result.map(|(o,)| o).unwrap_or(0) > 0)
     }
 
+    fn soft_delete(&self) -> bool {
+        self.soft_delete
+    }
+
     async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
-        sqlx::query(&r#"DELETE FROM %%TABLE_NAME%%"#.replace("%%TABLE_NAME%%", table_name))
+        sqlx::query(&self.query(r#"DELETE FROM %%TABLE_NAME%%"#, table_name))
             .execute(&self.pool)
             .await?;
         Ok(())
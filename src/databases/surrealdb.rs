@@ -1,6 +1,6 @@
 use crate::{DatabasePool, Session, SessionError, SessionStore};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, TimeZone, Utc};
 use std::collections::BTreeMap;
 use surrealdb::{sql::Value, Datastore, Error, Response, Session};
 
@@ -12,6 +12,7 @@ pub type SessionSurrealSessionStore = SessionStore<SessionSurrealPool>;
 pub struct SessionSurrealPool {
     connection_type: ConnectionType,
     session: Session,
+    soft_delete: bool,
 }
 
 enum ConnectionType {
@@ -40,6 +41,7 @@ impl SessionSurrealPool {
         Self {
             session,
             connection_type: ConnectionType::Memory,
+            soft_delete: false,
         }
     }
 
@@ -48,6 +50,7 @@ impl SessionSurrealPool {
         Self {
             session,
             connection_type: ConnectionType::File(format!("file://{}", path.as_ref())),
+            soft_delete: false,
         }
     }
 
@@ -56,6 +59,7 @@ impl SessionSurrealPool {
         Self {
             session,
             connection_type: ConnectionType::TiKV(format!("tikv://{}", uri.as_ref())),
+            soft_delete: false,
         }
     }
 
@@ -64,6 +68,7 @@ impl SessionSurrealPool {
         Self {
             session,
             connection_type: ConnectionType::Rockdb(format!("rocksdb://{}", uri.as_ref())),
+            soft_delete: false,
         }
     }
 
@@ -72,6 +77,7 @@ impl SessionSurrealPool {
         Self {
             session,
             connection_type: ConnectionType::Indxdb(format!("indxdb://{}", uri.as_ref())),
+            soft_delete: false,
         }
     }
 
@@ -80,6 +86,7 @@ impl SessionSurrealPool {
         Self {
             session,
             connection_type: ConnectionType::Fdb(format!("fdb://{}", uri.as_ref())),
+            soft_delete: false,
         }
     }
 
@@ -103,6 +110,16 @@ impl SessionSurrealPool {
         })
     }
 
+    /// Set's whether sessions are marked as deleted with a deleted_at field instead of being removed.
+    /// Default is false.
+    ///
+    /// This must match SessionConfig::with_soft_delete or SessionStore::try_new returns an error.
+    #[must_use]
+    pub fn with_soft_delete(mut self, soft_delete: bool) -> Self {
+        self.soft_delete = soft_delete;
+        self
+    }
+
     pub async fn is_valid(&self) -> Result<(), SessionError> {
         let connection = self.connect().await?;
         connection.execute("SELECT * FROM 1;", None, false).await?;
@@ -122,6 +139,7 @@ impl DatabasePool for SessionSurrealPool {
                     DEFINE FIELD id ON TABLE %%TABLE_NAME%% TYPE string ASSERT $value != NONE;
                     DEFINE FIELD expire ON TABLE %%TABLE_NAME%% TYPE int;
                     DEFINE FIELD session ON TABLE %%TABLE_NAME%% TYPE string ASSERT $value != NONE;
+                    DEFINE FIELD deleted_at ON TABLE %%TABLE_NAME%% TYPE int;
                     DEFINE INDEX %%TABLE_NAME%%IdIndex ON TABLE %%TABLE_NAME%% COLUMNS id UNIQUE;
                 "#
                 .replace("%%TABLE_NAME%%", table_name),
@@ -153,13 +171,60 @@ impl DatabasePool for SessionSurrealPool {
         Ok(())
     }
 
+    async fn soft_delete_by_expiry(&self, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return self.delete_by_expiry(table_name).await;
+        }
+
+        let conn = self.connect().await?;
+        let mut vars = BTreeMap::<String, Value>::new();
+
+        vars.insert("expires".to_string(), Utc::now().timestamp().into());
+
+        conn.ds
+            .execute(
+                &r#"UPDATE %%TABLE_NAME%% SET deleted_at = $expires WHERE expires < $expires AND deleted_at = NONE"#
+                    .replace("%%TABLE_NAME%%", table_name),
+                &ses,
+                Some(vars),
+                false,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn purge_deleted(&self, before: i64, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return Ok(());
+        }
+
+        let conn = self.connect().await?;
+        let mut vars = BTreeMap::<String, Value>::new();
+
+        vars.insert("before".to_string(), before.into());
+
+        conn.ds
+            .execute(
+                &r#"DELETE %%TABLE_NAME%% WHERE deleted_at != NONE AND deleted_at < $before"#
+                    .replace("%%TABLE_NAME%%", table_name),
+                &ses,
+                Some(vars),
+                false,
+            )
+            .await?;
+
+        Ok(())
+    }
+
     async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
         let conn = self.connect().await?;
 
         let mut res = conn
             .ds
             .execute(
-                &r#"SELECT COUNT() FROM %%TABLE_NAME%%"#.replace("%%TABLE_NAME%%", table_name),
+                &r#"SELECT COUNT() FROM %%TABLE_NAME%% WHERE deleted_at = NONE"#
+                    .replace("%%TABLE_NAME%%", table_name),
                 &ses,
                 None,
                 false,
@@ -194,7 +259,8 @@ impl DatabasePool for SessionSurrealPool {
                 (id, session, expires) VALUES $id, $session, $expires
                 ON DUPLICATE KEY UPDATE
                 expires = $expires,
-                session = $session
+                session = $session,
+                deleted_at = NONE
         "#
                 .replace("%%TABLE_NAME%%", table_name),
                 &ses,
@@ -218,7 +284,7 @@ impl DatabasePool for SessionSurrealPool {
             .execute(
                 &r#"
                 SELECT session FROM %%TABLE_NAME%%
-                WHERE id = $id AND (expires = NONE OR expires > $expires)
+                WHERE id = $id AND (expires = NONE OR expires > $expires) AND deleted_at = NONE
             "#
                 .replace("%%TABLE_NAME%%", table_name),
                 &ses,
@@ -234,6 +300,38 @@ impl DatabasePool for SessionSurrealPool {
         }
     }
 
+    async fn expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<DateTime<Utc>>, SessionError> {
+        let conn = self.connect().await?;
+        let mut vars = BTreeMap::<String, Value>::new();
+
+        vars.insert("id".to_string(), id.into());
+        vars.insert("expires".to_string(), Utc::now().timestamp().into());
+
+        let mut res = conn
+            .ds
+            .execute(
+                &r#"
+                SELECT expires FROM %%TABLE_NAME%%
+                WHERE id = $id AND (expires = NONE OR expires > $expires) AND deleted_at = NONE
+            "#
+                .replace("%%TABLE_NAME%%", table_name),
+                &ses,
+                Some(vars),
+                false,
+            )
+            .await?;
+
+        if let Some(response) = res.pop() {
+            Ok(Utc.timestamp_opt(response.result?.as_int(), 0).single())
+        } else {
+            Ok(None)
+        }
+    }
+
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
         let conn = self.connect().await?;
         let mut vars = BTreeMap::<String, Value>::new();
@@ -252,6 +350,30 @@ impl DatabasePool for SessionSurrealPool {
         Ok(())
     }
 
+    async fn soft_delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        if !self.soft_delete {
+            return self.delete_one_by_id(id, table_name).await;
+        }
+
+        let conn = self.connect().await?;
+        let mut vars = BTreeMap::<String, Value>::new();
+
+        vars.insert("id".to_string(), id.into());
+        vars.insert("deleted_at".to_string(), Utc::now().timestamp().into());
+
+        conn.ds
+            .execute(
+                &r#"UPDATE %%TABLE_NAME%% SET deleted_at = $deleted_at WHERE id = $id AND deleted_at = NONE"#
+                    .replace("%%TABLE_NAME%%", table_name),
+                &ses,
+                Some(vars),
+                false,
+            )
+            .await?;
+
+        Ok(())
+    }
+
     async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
         let conn = self.connect().await?;
         let mut vars = BTreeMap::<String, Value>::new();
@@ -262,7 +384,7 @@ impl DatabasePool for SessionSurrealPool {
         let mut res = conn
             .ds
             .execute(
-                &r#"SELECT COUNT() FROM %%TABLE_NAME%% WHERE id = $id AND (expires = NONE OR expires > $expires)"#.replace("%%TABLE_NAME%%", table_name),
+                &r#"SELECT COUNT() FROM %%TABLE_NAME%% WHERE id = $id AND (expires = NONE OR expires > $expires) AND deleted_at = NONE"#.replace("%%TABLE_NAME%%", table_name),
                 &ses,
                 Some(vars),
                 false,
@@ -276,6 +398,10 @@ impl DatabasePool for SessionSurrealPool {
         }
     }
 
+    fn soft_delete(&self) -> bool {
+        self.soft_delete
+    }
+
     async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
         let conn = self.connect().await?;
 
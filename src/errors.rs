@@ -36,4 +36,8 @@ pub enum SessionError {
     GenericDeleteError(String),
     #[error("{0}")]
     GenericNotSupportedError(String),
+    #[error("Session encryption error {0}")]
+    EncryptionError(String),
+    #[error("Session config error {0}")]
+    ConfigError(String),
 }
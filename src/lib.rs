@@ -5,20 +5,32 @@ mod config;
 pub mod databases;
 mod errors;
 mod layer;
+mod persist;
+mod rejection;
 mod service;
 mod session;
 mod session_data;
 mod session_store;
+mod session_writer;
+mod typed;
+mod versioned;
 
-pub use config::{Key, SameSite, SessionConfig, SessionMode};
+pub use config::{CookieVariant, Key, SameSite, SessionConfig, SessionMode};
 pub use databases::*;
 pub use errors::SessionError;
 pub use layer::SessionLayer;
-pub use session::Session;
-pub use session_store::SessionStore;
+pub use persist::{PersistSession, PersistSessionLayer, PersistSessionService};
+pub use rejection::SessionRejection;
+pub use session::{peek_session_id, Session, SessionExt};
+pub use session_data::SessionDataView;
+pub use session_store::{SessionStore, SessionStoreInfo};
+pub use typed::TypedSession;
+pub use versioned::SessionVersioned;
 
-pub(crate) use service::{CookiesExt, SessionService};
+pub(crate) use service::{get_cookies, CookiesExt, SessionService};
 pub(crate) use session_data::{SessionData, SessionID, SessionTimers};
+pub(crate) use session_writer::{SessionWrite, SessionWriter};
+pub(crate) use versioned::VersionedValue;
 
 #[cfg(test)]
 mod tests {
@@ -26,8 +38,8 @@ mod tests {
     use super::*;
     use axum::{
         body::Body,
-        http::{header, Request},
-        response::Redirect,
+        http::{header, Request, StatusCode},
+        response::{AppendHeaders, Redirect, Response},
         routing::get,
         Router,
     };
@@ -39,6 +51,139 @@ mod tests {
     };
     use tower::ServiceExt;
 
+    async fn call(app: &Router, uri: &str, cookies: &[header::HeaderValue]) -> Response {
+        call_with(app, uri, &[], cookies).await
+    }
+
+    async fn call_with(
+        app: &Router,
+        uri: &str,
+        headers: &[(header::HeaderName, &str)],
+        cookies: &[header::HeaderValue],
+    ) -> Response {
+        let mut request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        for (name, value) in headers {
+            request
+                .headers_mut()
+                .append(name, header::HeaderValue::from_str(value).unwrap());
+        }
+
+        for cookie in cookies {
+            request.headers_mut().append(header::COOKIE, cookie.clone());
+        }
+
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    fn set_cookies(response: &Response) -> Vec<header::HeaderValue> {
+        response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    fn session_id(cookies: &[header::HeaderValue]) -> String {
+        cookies
+            .iter()
+            .filter_map(|cookie| cookie.to_str().ok())
+            .find_map(|cookie| cookie.strip_prefix("sqlx_session="))
+            .and_then(|cookie| cookie.split(';').next())
+            .unwrap()
+            .to_owned()
+    }
+
+    async fn pg_pool() -> sqlx::PgPool {
+        let connect_opts = PgConnectOptions::new()
+            .database("postgres")
+            .username("postgres")
+            .password("password")
+            .host("localhost")
+            .port(5432);
+
+        PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(connect_opts)
+            .await
+            .unwrap()
+    }
+
+    async fn row_count(pool: &sqlx::PgPool, table_name: &str) -> i64 {
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table_name))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        count
+    }
+
+    /// A pool that keeps nothing and only counts how many times a session was stored.
+    #[derive(Debug, Clone, Default)]
+    struct CountingPool {
+        stores: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+        fail: bool,
+    }
+
+    impl CountingPool {
+        fn stores(&self) -> usize {
+            self.stores.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl DatabasePool for CountingPool {
+        async fn initiate(&self, _table_name: &str) -> Result<(), SessionError> {
+            Ok(())
+        }
+
+        async fn count(&self, _table_name: &str) -> Result<i64, SessionError> {
+            Ok(0)
+        }
+
+        async fn store(
+            &self,
+            _id: &str,
+            _session: &str,
+            _expires: i64,
+            _table_name: &str,
+        ) -> Result<(), SessionError> {
+            self.stores
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+            if self.fail {
+                Err(SessionError::ConfigError("store failed".to_owned()))
+            } else {
+                Ok(())
+            }
+        }
+
+        async fn load(&self, _id: &str, _table_name: &str) -> Result<Option<String>, SessionError> {
+            Ok(None)
+        }
+
+        async fn delete_one_by_id(&self, _id: &str, _table_name: &str) -> Result<(), SessionError> {
+            Ok(())
+        }
+
+        async fn exists(&self, _id: &str, _table_name: &str) -> Result<bool, SessionError> {
+            Ok(false)
+        }
+
+        async fn delete_by_expiry(&self, _table_name: &str) -> Result<(), SessionError> {
+            Ok(())
+        }
+
+        async fn delete_all(&self, _table_name: &str) -> Result<(), SessionError> {
+            Ok(())
+        }
+    }
+
+    async fn body(response: Response) -> String {
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
     #[tokio::test]
     async fn basic() {
         let config = SessionConfig::new()
@@ -130,4 +275,1123 @@ mod tests {
         let body = String::from_utf8(bytes.to_vec()).unwrap();
         assert_eq!(body, "Success");
     }
+
+    #[tokio::test]
+    async fn destroy_then_set() {
+        let config = SessionConfig::new();
+        let session_store = SessionStore::<SessionNullPool>::new(None, config);
+
+        let app = Router::new()
+            .route("/set", get(set))
+            .route("/destroy", get(destroy))
+            .route("/get", get(get_values))
+            .layer(SessionLayer::new(session_store));
+
+        async fn set(session: Session<SessionNullPool>) {
+            session.set("before", 1);
+        }
+
+        // Anything set after a destroy belongs to the Session that replaces it.
+        async fn destroy(session: Session<SessionNullPool>) {
+            session.set("removed", 2);
+            session.destroy();
+            session.set("after", 3);
+            session.remove("missing");
+        }
+
+        async fn get_values(session: Session<SessionNullPool>) -> String {
+            format!(
+                "{:?} {:?} {:?}",
+                session.get::<i32>("before"),
+                session.get::<i32>("removed"),
+                session.get::<i32>("after")
+            )
+        }
+
+        let response = call(&app, "/set", &[]).await;
+        let first_cookies = set_cookies(&response);
+
+        let response = call(&app, "/destroy", &first_cookies).await;
+        let second_cookies = set_cookies(&response);
+        assert_ne!(session_id(&first_cookies), session_id(&second_cookies));
+
+        let response = call(&app, "/get", &second_cookies).await;
+        assert_eq!(body(response).await, "None None Some(3)");
+    }
+
+    #[tokio::test]
+    async fn rejection_json() {
+        let app = Router::new().route("/", get(|_: Session<SessionNullPool>| async {}));
+
+        let response = call_with(&app, "/", &[(header::ACCEPT, "application/json")], &[]).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body: serde_json::Value = serde_json::from_str(&body(response).await).unwrap();
+        assert_eq!(body["code"], "session_layer_missing");
+
+        let response = call(&app, "/", &[]).await;
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+    }
+
+    #[tokio::test]
+    async fn csrf_double_submit() {
+        let config = SessionConfig::new().with_csrf_cookie("csrf_token");
+        let session_store = SessionStore::<SessionNullPool>::new(None, config);
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(
+                    |session: Session<SessionNullPool>, request: Request<Body>| async move {
+                        let token = request
+                            .headers()
+                            .get("x-csrf-token")
+                            .and_then(|value| value.to_str().ok())
+                            .unwrap_or("");
+
+                        session.verify_double_submit(token).to_string()
+                    },
+                ),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let cookies = set_cookies(&call(&app, "/", &[]).await);
+        let csrf = cookies
+            .iter()
+            .filter_map(|cookie| cookie.to_str().ok())
+            .find(|cookie| cookie.starts_with("csrf_token="))
+            .unwrap();
+        assert!(!csrf.contains("HttpOnly"));
+        let token = csrf["csrf_token=".len()..]
+            .split(';')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let mut responses = Vec::new();
+
+        for value in ["wrong", token.as_str()] {
+            let csrf_header = header::HeaderName::from_static("x-csrf-token");
+            let response = call_with(&app, "/", &[(csrf_header, value)], &cookies).await;
+            responses.push(body(response).await);
+        }
+
+        assert_eq!(responses, ["false", "true"]);
+    }
+
+    #[tokio::test]
+    async fn cookie_skip_status() {
+        let config = SessionConfig::new().with_cookie_skip_status(StatusCode::NOT_MODIFIED);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config);
+
+        let app = Router::new()
+            .route("/", get(|| async { StatusCode::OK }))
+            .route("/cached", get(|| async { StatusCode::NOT_MODIFIED }))
+            .layer(SessionLayer::new(session_store));
+
+        for (uri, cookies) in [("/", true), ("/cached", false)] {
+            let response = call(&app, uri, &[]).await;
+            assert_eq!(!set_cookies(&response).is_empty(), cookies);
+        }
+    }
+
+    #[test]
+    fn versioned_fallback() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Theme {
+            Light,
+            Dark,
+        }
+
+        impl SessionVersioned for Theme {
+            const VERSION: u32 = 2;
+
+            fn fallback() -> Self {
+                Theme::Light
+            }
+
+            fn migrate(version: u32, value: serde_json::Value) -> Option<Self> {
+                match (version, value.as_str()) {
+                    (1, Some("Night")) => Some(Theme::Dark),
+                    _ => serde_json::from_value(value).ok(),
+                }
+            }
+        }
+
+        let stored = serde_json::to_value(VersionedValue::new(Theme::Dark)).unwrap();
+        assert_eq!(VersionedValue::<Theme>::decode(stored), Theme::Dark);
+
+        let old = serde_json::json!({ "version": 1, "value": "Night" });
+        assert_eq!(VersionedValue::<Theme>::decode(old), Theme::Dark);
+
+        let renamed = serde_json::json!({ "version": 2, "value": "Sepia" });
+        assert_eq!(VersionedValue::<Theme>::decode(renamed), Theme::Light);
+
+        let unversioned = serde_json::json!("Dark");
+        assert_eq!(VersionedValue::<Theme>::decode(unversioned), Theme::Dark);
+    }
+
+    #[tokio::test]
+    async fn typed_session() {
+        #[derive(Default, Serialize, Deserialize)]
+        struct AppSession {
+            visits: u64,
+        }
+
+        type AppTypedSession = TypedSession<AppSession, SessionNullPool>;
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new());
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|mut session: AppTypedSession| async move {
+                    session.visits += 1;
+                    let visits = session.visits.to_string();
+                    (session, visits)
+                }),
+            )
+            .route(
+                "/raw",
+                get(|session: Session<SessionNullPool>| async move {
+                    session
+                        .get::<serde_json::Value>(typed::TYPED_SESSION_KEY)
+                        .unwrap()
+                        .to_string()
+                }),
+            )
+            .route(
+                "/corrupt",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set(typed::TYPED_SESSION_KEY, "not an AppSession");
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = call(&app, "/", &[]).await;
+        let cookies = set_cookies(&response);
+        assert_eq!(body(response).await, "1");
+        assert_eq!(body(call(&app, "/", &cookies).await).await, "2");
+
+        // Data which no longer deserializes is rejected instead of being replaced by the default.
+        call(&app, "/corrupt", &cookies).await;
+        let response = call(&app, "/", &cookies).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            body(call(&app, "/raw", &cookies).await).await,
+            r#""not an AppSession""#
+        );
+    }
+
+    #[test]
+    fn remove_missing_key_stays_clean() {
+        let config = SessionConfig::new();
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.set("present", 1, &config);
+        session.update = false;
+
+        session.remove("missing");
+        let _ = session.get_remove::<i32>("missing");
+        session.set("present", 1, &config);
+        assert!(!session.update);
+
+        session.remove("present");
+        assert!(session.update);
+    }
+
+    #[test]
+    fn set_null() {
+        let config = SessionConfig::new();
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.set("x", Some(1), &config);
+        session.set("x", None::<i32>, &config);
+        assert_eq!(session.get::<Option<i32>>("x"), Some(None));
+
+        let config = SessionConfig::new().with_null_as_remove(true);
+        session.set("x", None::<i32>, &config);
+        assert!(!session.data.contains_key("x"));
+    }
+
+    #[tokio::test]
+    async fn file_pool() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let pool = SessionFilePool::new(&dir).with_soft_delete(true);
+        let id = uuid::Uuid::new_v4().to_string();
+        let expired = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        pool.initiate("sessions").await.unwrap();
+        pool.store(&id, "live", now + 60, "sessions").await.unwrap();
+        pool.store(&expired, "old", now - 60, "sessions")
+            .await
+            .unwrap();
+        // Expired sessions are not counted even before they are removed.
+        assert_eq!(pool.count("sessions").await.unwrap(), 1);
+        assert_eq!(std::fs::read_dir(dir.join("sessions")).unwrap().count(), 2);
+        assert_eq!(
+            pool.load(&id, "sessions").await.unwrap().as_deref(),
+            Some("live")
+        );
+        assert_eq!(pool.load(&expired, "sessions").await.unwrap(), None);
+        assert_eq!(pool.load("../escape", "sessions").await.unwrap(), None);
+
+        pool.delete_by_expiry("sessions").await.unwrap();
+        assert_eq!(pool.count("sessions").await.unwrap(), 1);
+        assert_eq!(std::fs::read_dir(dir.join("sessions")).unwrap().count(), 1);
+
+        pool.soft_delete_one_by_id(&id, "sessions").await.unwrap();
+        assert!(!pool.exists(&id, "sessions").await.unwrap());
+        pool.purge_deleted(now + 120, "sessions").await.unwrap();
+        assert_eq!(pool.count("sessions").await.unwrap(), 0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cookie_variants() {
+        let config = SessionConfig::new()
+            .with_cookie_same_site(SameSite::None)
+            .with_secure(true)
+            .with_key(Key::generate())
+            .with_cookie_variant(CookieVariant::new("_legacy"));
+        let session_store = SessionStore::<SessionNullPool>::new(None, config);
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+                    session.set("visits", visits);
+                    visits.to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let cookies = set_cookies(&call(&app, "/", &[]).await);
+        let cookie = |name: &str| {
+            cookies
+                .iter()
+                .filter_map(|cookie| cookie.to_str().ok())
+                .find(|cookie| cookie.starts_with(name))
+                .unwrap()
+        };
+
+        let main = cookie("sqlx_session=");
+        let legacy = cookie("sqlx_session_legacy=");
+        assert!(main.contains("SameSite=None"));
+        assert!(!legacy.contains("SameSite"));
+
+        // A browser that dropped the SameSite=None cookie still finds its session.
+        let legacy = header::HeaderValue::from_str(legacy.split(';').next().unwrap()).unwrap();
+        assert_eq!(body(call(&app, "/", &[legacy]).await).await, "2");
+    }
+
+    #[tokio::test]
+    async fn oversized_cookie() {
+        // A Session ID is 36 characters so the Session cookie is over the first limit and exactly at the second.
+        for (limit, kept) in [(20, false), (36, true)] {
+            let config = SessionConfig::new().with_cookie_max_value_length(limit);
+            let session_store = SessionStore::<SessionNullPool>::new(None, config);
+
+            let app = Router::new()
+                .route(
+                    "/",
+                    get(|session: Session<SessionNullPool>| async move {
+                        let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+                        session.set("visits", visits);
+                        visits.to_string()
+                    }),
+                )
+                .layer(SessionLayer::new(session_store));
+
+            let response = call(&app, "/", &[]).await;
+            let mut first_cookies = set_cookies(&response);
+            assert_eq!(body(response).await, "1");
+
+            // Only the cookie over the limit is dropped, not the rest of the header.
+            let padding = format!("padding={}", "x".repeat(37));
+            first_cookies.push(header::HeaderValue::from_str(&padding).unwrap());
+
+            let response = call(&app, "/", &first_cookies).await;
+            let second_cookies = set_cookies(&response);
+            assert_eq!(
+                session_id(&first_cookies) == session_id(&second_cookies),
+                kept
+            );
+            assert_eq!(body(response).await, if kept { "2" } else { "1" });
+        }
+    }
+
+    #[tokio::test]
+    async fn pg_soft_delete() {
+        let table = "test_soft_delete";
+        let pg = pg_pool().await;
+        sqlx::query(&format!("DROP TABLE IF EXISTS {}", table))
+            .execute(&pg)
+            .await
+            .unwrap();
+
+        let pool = SessionPgPool::from(pg.clone()).with_soft_delete(true);
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        pool.initiate(table).await.unwrap();
+        pool.store(&id, "data", now + 60, table).await.unwrap();
+        assert_eq!(
+            pool.load(&id, table).await.unwrap().as_deref(),
+            Some("data")
+        );
+
+        pool.soft_delete_one_by_id(&id, table).await.unwrap();
+        assert_eq!(pool.load(&id, table).await.unwrap(), None);
+        assert!(!pool.exists(&id, table).await.unwrap());
+        assert_eq!(pool.count(table).await.unwrap(), 0);
+        assert_eq!(row_count(&pg, table).await, 1);
+
+        pool.purge_deleted(now + 120, table).await.unwrap();
+        assert_eq!(row_count(&pg, table).await, 0);
+    }
+
+    #[tokio::test]
+    async fn pg_table_without_deleted_at() {
+        // The layout created by releases before soft delete existed.
+        let table = "test_legacy_table";
+        let pg = pg_pool().await;
+        sqlx::query(&format!("DROP TABLE IF EXISTS {}", table))
+            .execute(&pg)
+            .await
+            .unwrap();
+        sqlx::query(&format!(
+            "CREATE TABLE {} (id VARCHAR(128) NOT NULL PRIMARY KEY, expires INTEGER NULL, session TEXT NOT NULL)",
+            table
+        ))
+        .execute(&pg)
+        .await
+        .unwrap();
+
+        let pool = SessionPgPool::from(pg.clone());
+        let id = uuid::Uuid::new_v4().to_string();
+        let expired = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        pool.initiate(table).await.unwrap();
+        pool.store(&id, "data", now + 60, table).await.unwrap();
+        pool.store(&id, "updated", now + 60, table).await.unwrap();
+        pool.store(&expired, "old", now - 60, table).await.unwrap();
+        assert_eq!(
+            pool.load(&id, table).await.unwrap().as_deref(),
+            Some("updated")
+        );
+        assert!(pool.exists(&id, table).await.unwrap());
+        assert_eq!(pool.count(table).await.unwrap(), 2);
+
+        pool.soft_delete_by_expiry(table).await.unwrap();
+        pool.purge_deleted(now + 120, table).await.unwrap();
+        assert_eq!(row_count(&pg, table).await, 1);
+
+        pool.soft_delete_one_by_id(&id, table).await.unwrap();
+        assert_eq!(row_count(&pg, table).await, 0);
+    }
+
+    #[tokio::test]
+    async fn session_ext() {
+        async fn middleware<B>(request: Request<B>, next: axum::middleware::Next<B>) -> Response {
+            if let Some(session) = request.session::<SessionNullPool>() {
+                session.set("middleware", true);
+            }
+
+            next.run(request).await
+        }
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new());
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    format!("{:?}", session.get::<bool>("middleware"))
+                }),
+            )
+            .layer(axum::middleware::from_fn(middleware))
+            .layer(SessionLayer::new(session_store));
+
+        let response = call(&app, "/", &[]).await;
+        assert_eq!(body(response).await, "Some(true)");
+
+        let request = Request::builder().uri("/").body(()).unwrap();
+        assert!(request.session::<SessionNullPool>().is_none());
+    }
+
+    #[tokio::test]
+    async fn routed_pool() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let first = SessionFilePool::new(dir.join("first"));
+        let second = SessionFilePool::new(dir.join("second"));
+        let pool = SessionRoutedPool::new(|id| {
+            if id.starts_with('1') {
+                "second".into()
+            } else if id.starts_with('2') {
+                "missing".into()
+            } else {
+                "first".into()
+            }
+        })
+        .with_pool("first", first.clone())
+        .with_pool("second", second.clone());
+
+        // Picks which pool a new Session ID is routed to by its first character.
+        let id = |prefix: char| format!("{}{}", prefix, &uuid::Uuid::new_v4().to_string()[1..]);
+        let (first_1, first_2, first_3) = (id('0'), id('0'), id('0'));
+        let (second_1, missing_1) = (id('1'), id('2'));
+        let config = SessionConfig::new();
+        let session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        let stored = serde_json::to_string(&session).unwrap();
+        let expires = chrono::Utc::now().timestamp() + 60;
+
+        pool.initiate("sessions").await.unwrap();
+        pool.store(&first_1, &stored, expires, "sessions")
+            .await
+            .unwrap();
+        pool.store(&second_1, &stored, expires, "sessions")
+            .await
+            .unwrap();
+        assert_eq!(first.count("sessions").await.unwrap(), 1);
+        assert_eq!(second.count("sessions").await.unwrap(), 1);
+        assert_eq!(pool.count("sessions").await.unwrap(), 2);
+        assert_eq!(
+            pool.load(&second_1, "sessions").await.unwrap(),
+            Some(stored.clone())
+        );
+
+        // The payload is never read so anything can be routed, including encrypted sessions.
+        pool.store(&first_2, "not json", expires, "sessions")
+            .await
+            .unwrap();
+        assert_eq!(first.count("sessions").await.unwrap(), 2);
+
+        // A session stored before the routing changed is still found in its old pool.
+        second
+            .store(&first_3, &stored, expires, "sessions")
+            .await
+            .unwrap();
+        assert!(pool.exists(&first_3, "sessions").await.unwrap());
+        assert_eq!(
+            pool.load(&first_3, "sessions").await.unwrap(),
+            Some(stored.clone())
+        );
+        pool.delete_one_by_id(&first_3, "sessions").await.unwrap();
+        assert!(!second.exists(&first_3, "sessions").await.unwrap());
+
+        assert!(pool
+            .store(&missing_1, &stored, expires, "sessions")
+            .await
+            .is_err());
+
+        pool.delete_one_by_id(&second_1, "sessions").await.unwrap();
+        assert!(!pool.exists(&second_1, "sessions").await.unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cache_control() {
+        let config = SessionConfig::new().with_cache_control("private");
+        let session_store = SessionStore::<SessionNullPool>::new(None, config);
+
+        let app = Router::new()
+            .route("/", get(|| async {}))
+            .route(
+                "/public",
+                get(|| async { [(header::CACHE_CONTROL, "public, max-age=60")] }),
+            )
+            .route(
+                "/max-age",
+                get(|| async { [(header::CACHE_CONTROL, "max-age=60")] }),
+            )
+            .route(
+                "/split",
+                get(|| async {
+                    AppendHeaders([
+                        (header::CACHE_CONTROL, "max-age=60"),
+                        (header::CACHE_CONTROL, "must-revalidate"),
+                    ])
+                }),
+            )
+            .route(
+                "/split-no-store",
+                get(|| async {
+                    AppendHeaders([
+                        (header::CACHE_CONTROL, "max-age=60"),
+                        (header::CACHE_CONTROL, "no-store"),
+                    ])
+                }),
+            )
+            .route(
+                "/opaque",
+                get(|| async {
+                    let value = header::HeaderValue::from_bytes(b"max-age=60\xff").unwrap();
+                    [(header::CACHE_CONTROL, value)]
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        for (uri, expected) in [
+            ("/", &b"private"[..]),
+            ("/public", b"public, max-age=60"),
+            ("/max-age", b"max-age=60, private"),
+            ("/split", b"max-age=60, must-revalidate, private"),
+            ("/split-no-store", b"max-age=60, no-store"),
+            ("/opaque", b"max-age=60\xff"),
+        ] {
+            let response = call(&app, uri, &[]).await;
+            assert!(response.headers().contains_key(header::SET_COOKIE));
+            let values: Vec<_> = response
+                .headers()
+                .get_all(header::CACHE_CONTROL)
+                .iter()
+                .map(|value| value.as_bytes())
+                .collect();
+            assert_eq!(values.join(&b", "[..]), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn expiry_of() {
+        let config = SessionConfig::new().with_table_name("test_expiry");
+        let session_store =
+            SessionStore::<SessionPgPool>::new(Some(pg_pool().await.into()), config);
+        session_store.initiate().await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionPgPool>| async move {
+                    session.set("value", 1);
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = call(&app, "/", &[]).await;
+        let id = session_id(&set_cookies(&response));
+        let expires = session_store.expiry_of(&id).await.unwrap().unwrap();
+        let lifespan = expires - chrono::Utc::now();
+        assert!(lifespan > chrono::Duration::hours(5) && lifespan <= chrono::Duration::hours(6));
+
+        let missing = uuid::Uuid::new_v4().to_string();
+        assert_eq!(session_store.expiry_of(&missing).await.unwrap(), None);
+
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let pool = SessionFilePool::new(&dir);
+        let expires = chrono::Utc::now().timestamp() + 60;
+        pool.initiate("sessions").await.unwrap();
+        pool.store(&id, "data", expires, "sessions").await.unwrap();
+        assert_eq!(
+            pool.expiry(&id, "sessions")
+                .await
+                .unwrap()
+                .map(|e| e.timestamp()),
+            Some(expires)
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let session_store = SessionStore::new(Some(CountingPool::default()), SessionConfig::new());
+        assert!(matches!(
+            session_store.expiry_of(&id).await,
+            Err(SessionError::GenericNotSupportedError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn single_writer_combines_writes() {
+        let config = SessionConfig::new().with_single_writer(true);
+        let pool = CountingPool::default();
+        let session_store = SessionStore::new(Some(pool.clone()), config.clone());
+        let session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session_store
+            .inner
+            .insert(session.id.to_string(), session.clone());
+
+        // Every write is queued before the writer gets to run so only the last is stored.
+        let writes = (0..5).map(|_| session_store.persist_session(&session));
+        for result in futures::future::join_all(writes).await {
+            result.unwrap();
+        }
+        assert_eq!(pool.stores(), 1);
+
+        session_store.persist_session(&session).await.unwrap();
+        assert_eq!(pool.stores(), 2);
+
+        // A session unloaded from memory before its write runs is still stored.
+        session_store.inner.remove(&session.id.to_string());
+        session_store.persist_session(&session).await.unwrap();
+        assert_eq!(pool.stores(), 3);
+    }
+
+    #[tokio::test]
+    async fn single_writer_errors() {
+        let config = SessionConfig::new().with_single_writer(true);
+        let pool = CountingPool {
+            fail: true,
+            ..Default::default()
+        };
+        let session_store = SessionStore::new(Some(pool), config.clone());
+        let session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session_store
+            .inner
+            .insert(session.id.to_string(), session.clone());
+
+        let writes = (0..3).map(|_| session_store.persist_session(&session));
+        let results = futures::future::join_all(writes).await;
+        assert!(matches!(
+            results[0],
+            Err(SessionError::GenericInsertError(ref error)) if error == "Session config error store failed"
+        ));
+        assert!(matches!(
+            results[1],
+            Err(SessionError::GenericInsertError(_))
+        ));
+        assert!(matches!(results[2], Err(SessionError::ConfigError(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn single_writer_replaced_after_idle() {
+        let config = SessionConfig::new().with_single_writer(true);
+        let pool = CountingPool::default();
+        let session_store = SessionStore::new(Some(pool.clone()), config.clone());
+        let session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        let id = session.id.to_string();
+        session_store.inner.insert(id.clone(), session.clone());
+
+        session_store.persist_session(&session).await.unwrap();
+        assert!(session_store.writers.contains_key(&id));
+
+        tokio::time::sleep(std::time::Duration::from_secs(31)).await;
+        assert!(!session_store.writers.contains_key(&id));
+
+        session_store.persist_session(&session).await.unwrap();
+        assert!(session_store.writers.contains_key(&id));
+        assert_eq!(pool.stores(), 2);
+    }
+
+    #[tokio::test]
+    async fn last_accessed_resolution() {
+        let mut stores = Vec::new();
+
+        for resolution in [chrono::Duration::zero(), chrono::Duration::hours(1)] {
+            let config = SessionConfig::new().with_last_accessed_resolution(resolution);
+            let pool = CountingPool::default();
+            let session_store = SessionStore::new(Some(pool.clone()), config);
+
+            let app = Router::new()
+                .route("/", get(|_: Session<CountingPool>| async {}))
+                .layer(SessionLayer::new(session_store));
+
+            let response = call(&app, "/", &[]).await;
+            call(&app, "/", &set_cookies(&response)).await;
+            stores.push(pool.stores());
+        }
+
+        // The second visit changes nothing but last_accessed, which is only saved once it is stale.
+        assert_eq!(stores, [2, 1]);
+
+        let config = SessionConfig::new();
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.last_accessed = session.stored_last_accessed + chrono::Duration::minutes(30);
+        assert!(!session.last_accessed_stale(&config));
+        session.last_accessed = session.stored_last_accessed + chrono::Duration::hours(2);
+        assert!(session.last_accessed_stale(&config));
+
+        // Sessions saved before last_accessed existed still load.
+        let mut stored = serde_json::to_value(&session).unwrap();
+        stored.as_object_mut().unwrap().remove("last_accessed");
+        let loaded: SessionData = serde_json::from_value(stored).unwrap();
+        assert!(loaded.last_accessed <= chrono::Utc::now());
+    }
+
+    #[tokio::test]
+    async fn encrypted_pool() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        /// Wraps its one data key by flipping every bit.
+        struct TestKeyProvider;
+
+        #[async_trait::async_trait]
+        impl KeyProvider for TestKeyProvider {
+            async fn data_key(&self) -> Result<(String, Vec<u8>, Vec<u8>), SessionError> {
+                let key = vec![7; 32];
+                let wrapped = key.iter().map(|byte| !byte).collect();
+                Ok(("test".to_owned(), key, wrapped))
+            }
+
+            async fn unwrap(
+                &self,
+                key_id: &str,
+                wrapped_key: &[u8],
+            ) -> Result<Vec<u8>, SessionError> {
+                assert_eq!(key_id, "test");
+                Ok(wrapped_key.iter().map(|byte| !byte).collect())
+            }
+        }
+
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let inner = SessionFilePool::new(&dir);
+        let pool = SessionEncryptedPool::new(inner.clone(), TestKeyProvider);
+        let id = uuid::Uuid::new_v4().to_string();
+        let expires = chrono::Utc::now().timestamp() + 60;
+
+        pool.initiate("sessions").await.unwrap();
+        pool.store(&id, "secret data", expires, "sessions")
+            .await
+            .unwrap();
+        let stored = inner.load(&id, "sessions").await.unwrap().unwrap();
+        assert!(!stored.contains("secret data"));
+        assert_eq!(
+            pool.load(&id, "sessions").await.unwrap().as_deref(),
+            Some("secret data")
+        );
+
+        // An envelope moved to another session is rejected as it is bound to its Session ID.
+        let other = uuid::Uuid::new_v4().to_string();
+        inner
+            .store(&other, &stored, expires, "sessions")
+            .await
+            .unwrap();
+        assert!(matches!(
+            pool.load(&other, "sessions").await,
+            Err(SessionError::EncryptionError(_))
+        ));
+
+        let envelope: serde_json::Value = serde_json::from_str(&stored).unwrap();
+
+        let mut tampered = envelope.clone();
+        let mut ciphertext = STANDARD
+            .decode(envelope["ciphertext"].as_str().unwrap())
+            .unwrap();
+        ciphertext[0] ^= 1;
+        tampered["ciphertext"] = STANDARD.encode(ciphertext).into();
+
+        let mut short_nonce = envelope;
+        short_nonce["nonce"] = STANDARD.encode([0; 8]).into();
+
+        for envelope in [tampered, short_nonce] {
+            inner
+                .store(&id, &envelope.to_string(), expires, "sessions")
+                .await
+                .unwrap();
+            assert!(matches!(
+                pool.load(&id, "sessions").await,
+                Err(SessionError::EncryptionError(_))
+            ));
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn peek_session() {
+        let config = SessionConfig::new().with_key(Key::generate());
+        let session_store = SessionStore::<SessionNullPool>::new(None, config.clone());
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move { session.id.inner() }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = call(&app, "/", &[]).await;
+        let cookies = set_cookies(&response);
+        let id = body(response).await;
+
+        let mut request = Request::builder().uri("/").body(()).unwrap();
+        for cookie in &cookies {
+            request.headers_mut().append(header::COOKIE, cookie.clone());
+        }
+        let (parts, _) = request.into_parts();
+
+        assert_eq!(
+            peek_session_id(&parts, &config).map(|id| id.to_string()),
+            Some(id)
+        );
+        // The cookie can not be read without the Key it was encrypted with.
+        assert_eq!(peek_session_id(&parts, &SessionConfig::new()), None);
+
+        let (parts, _) = Request::builder().uri("/").body(()).unwrap().into_parts();
+        assert_eq!(peek_session_id(&parts, &config), None);
+    }
+
+    #[test]
+    fn require_key() {
+        let config = SessionConfig::new().with_require_key(true);
+        assert!(matches!(
+            SessionStore::<SessionNullPool>::try_new(None, config.clone()),
+            Err(SessionError::ConfigError(_))
+        ));
+        assert!(
+            std::panic::catch_unwind(|| SessionStore::<SessionNullPool>::new(None, config.clone()))
+                .is_err()
+        );
+
+        let config = config.with_key(Key::generate());
+        assert!(SessionStore::<SessionNullPool>::try_new(None, config).is_ok());
+    }
+
+    #[test]
+    fn session_columns() {
+        let columns = SessionColumns::new().with_id("session_id");
+        columns.validate(63).unwrap();
+        assert_eq!(
+            columns.apply(
+                "SELECT %%ID%% FROM %%TABLE_NAME%%",
+                "public.sessions",
+                '"',
+                false
+            ),
+            r#"SELECT "session_id" FROM public.sessions"#
+        );
+        assert_eq!(
+            columns.apply(
+                "SELECT COUNT(*) FROM %%TABLE_NAME%% %%WHERE_NOT_DELETED%%",
+                "sessions",
+                '`',
+                true
+            ),
+            "SELECT COUNT(*) FROM sessions WHERE `deleted_at` IS NULL"
+        );
+
+        for name in ["", "1id", "id; DROP TABLE x", "i\"d", "%%TABLE_NAME%%"] {
+            assert!(SessionColumns::new().with_id(name).validate(63).is_err());
+        }
+
+        // Postgres allows one byte less than MySql and Sqlite.
+        let long = "a".repeat(64);
+        assert!(SessionColumns::new()
+            .with_id(long.clone())
+            .validate(64)
+            .is_ok());
+        assert!(SessionColumns::new()
+            .with_id(long.clone())
+            .validate(63)
+            .is_err());
+
+        SessionColumns::validate_table("public.sessions", 63).unwrap();
+        for table_name in [
+            "",
+            "sessions; DROP TABLE x",
+            "a..b",
+            "a.b.c",
+            "%%ID%%",
+            "sessions--",
+            &long,
+        ] {
+            assert!(matches!(
+                SessionColumns::validate_table(table_name, 63),
+                Err(SessionError::ConfigError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn pinned_keys() {
+        let config = SessionConfig::new();
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.set("device-id", 7, &config);
+        session.set("cart", 3, &config);
+        session.pin("device-id");
+        session.pin("not-set-yet");
+
+        // Pins are saved with the Session so they still apply once it is loaded again.
+        let stored = serde_json::to_string(&session).unwrap();
+        let mut session: SessionData = serde_json::from_str(&stored).unwrap();
+        assert!(session.pinned.contains("device-id") && session.pinned.contains("not-set-yet"));
+
+        session.clear();
+        assert_eq!(session.get::<i32>("device-id"), Some(7));
+        assert_eq!(session.get::<i32>("cart"), None);
+
+        session.unpin("device-id");
+        session.clear();
+        assert_eq!(session.get::<i32>("device-id"), None);
+    }
+
+    #[tokio::test]
+    async fn persist_marked_only() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let pool = SessionFilePool::new(&dir);
+        let config = SessionConfig::new().with_persist_marked_only(true);
+        let session_store = SessionStore::new(Some(pool.clone()), config);
+        session_store.initiate().await.unwrap();
+
+        async fn set(session: Session<SessionFilePool>) {
+            session.set("value", 1);
+        }
+
+        let app = Router::new()
+            .route("/", get(set))
+            .route("/layer", get(set).layer(PersistSessionLayer))
+            .route(
+                "/returned",
+                get(|session: Session<SessionFilePool>| async move {
+                    session.set("value", 1);
+                    (PersistSession, "")
+                }),
+            )
+            .route(
+                "/persist",
+                get(|session: Session<SessionFilePool>| async move {
+                    session.set("value", 1);
+                    session.persist();
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = call(&app, "/", &[]).await;
+        let cookies = set_cookies(&response);
+        assert_eq!(pool.count("async_sessions").await.unwrap(), 0);
+
+        // The change made on the unmarked route is saved by the next marked request.
+        call(&app, "/layer", &cookies).await;
+        assert_eq!(pool.count("async_sessions").await.unwrap(), 1);
+        let stored = pool
+            .load(&session_id(&cookies), "async_sessions")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(stored.contains("value"));
+
+        call(&app, "/returned", &[]).await;
+        assert_eq!(pool.count("async_sessions").await.unwrap(), 2);
+
+        call(&app, "/persist", &[]).await;
+        assert_eq!(pool.count("async_sessions").await.unwrap(), 3);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn session_view() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new());
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<SessionNullPool>| async move {
+                    session.set("value", 1);
+                    session.pin("value");
+                    session.set_longterm(true);
+                    let view = session.view().unwrap();
+
+                    // The view is a snapshot so later changes are not within it.
+                    session.set("value", 2);
+
+                    format!(
+                        "{} {:?} {} {} {}",
+                        view.id() == session.id.0,
+                        view.get::<i32>("value"),
+                        view.pinned().contains("value"),
+                        view.longterm(),
+                        serde_json::to_value(&view).unwrap()["data"]["value"]
+                    )
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = call(&app, "/", &[]).await;
+        assert_eq!(body(response).await, "true Some(1) true true \"1\"");
+    }
+
+    #[tokio::test]
+    async fn privilege_change() {
+        for renew in [true, false] {
+            let config = SessionConfig::new().with_renew_on_privilege_change(renew);
+            let session_store = SessionStore::<SessionNullPool>::new(None, config);
+
+            let app = Router::new()
+                .route(
+                    "/login",
+                    get(|session: Session<SessionNullPool>| async move {
+                        session.set("user-id", 5);
+                        session.mark_privilege_change();
+                    }),
+                )
+                .route(
+                    "/",
+                    get(|session: Session<SessionNullPool>| async move {
+                        format!("{:?}", session.get::<i32>("user-id"))
+                    }),
+                )
+                .layer(SessionLayer::new(session_store));
+
+            let first_cookies = set_cookies(&call(&app, "/", &[]).await);
+            let response = call(&app, "/login", &first_cookies).await;
+            let second_cookies = set_cookies(&response);
+            assert_eq!(
+                session_id(&first_cookies) != session_id(&second_cookies),
+                renew
+            );
+
+            let response = call(&app, "/", &second_cookies).await;
+            assert_eq!(body(response).await, "Some(5)");
+        }
+    }
+
+    #[test]
+    fn describe() {
+        let config = SessionConfig::new()
+            .with_table_name("sessions")
+            .with_key(Key::generate())
+            .with_cookie_max_value_length(1024)
+            .with_cache_control("private")
+            .with_null_as_remove(true)
+            .with_renew_on_privilege_change(false)
+            .with_cookie_skip_status(StatusCode::NOT_MODIFIED)
+            .with_cookie_skip_content_type("image/png")
+            .with_cookie_variant(CookieVariant::new("_legacy"));
+        let info = SessionStore::<SessionNullPool>::new(None, config).describe();
+
+        assert!(info.backend.ends_with("SessionNullPool"));
+        assert!(!info.persistent);
+        assert_eq!(info.table_name, "sessions");
+        assert!(info.encrypted_cookies);
+        assert_eq!(info.cookie_max_value_length, 1024);
+        assert_eq!(info.cache_control.as_deref(), Some("private"));
+        assert!(info.null_as_remove);
+        assert!(!info.renew_on_privilege_change);
+        assert_eq!(info.cookie_skip_statuses, [304]);
+        assert_eq!(info.cookie_skip_content_types, ["image/png"]);
+        assert_eq!(info.cookie_variants, ["sqlx_session_legacy"]);
+        assert_eq!(info.lifespan, 6 * 60 * 60);
+        assert!(!info.soft_delete);
+
+        let pool = SessionFilePool::new(std::env::temp_dir()).with_soft_delete(true);
+        let config = SessionConfig::new().with_soft_delete(true);
+        assert!(SessionStore::new(Some(pool), config).describe().soft_delete);
+
+        // The Key itself is never described.
+        let json = serde_json::to_value(&info).unwrap();
+        assert!(json.get("key").is_none());
+    }
+
+    #[test]
+    fn soft_delete_must_match() {
+        let pool = SessionFilePool::new(std::env::temp_dir());
+
+        for (config, pool) in [
+            (true, pool.clone()),
+            (false, pool.clone().with_soft_delete(true)),
+        ] {
+            let config = SessionConfig::new().with_soft_delete(config);
+            assert!(matches!(
+                SessionStore::try_new(Some(pool), config),
+                Err(SessionError::ConfigError(_))
+            ));
+        }
+
+        let config = SessionConfig::new().with_soft_delete(true);
+        assert!(SessionStore::try_new(Some(pool.with_soft_delete(true)), config.clone()).is_ok());
+        assert!(SessionStore::<SessionNullPool>::try_new(None, config).is_ok());
+    }
 }
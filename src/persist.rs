@@ -0,0 +1,92 @@
+use axum_core::response::{IntoResponseParts, Response, ResponseParts};
+use futures::future::BoxFuture;
+use http::Request;
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Marks a response so its Session is saved to the database when
+/// SessionConfig::with_persist_marked_only is enabled.
+///
+/// This is added to the response's extensions by PersistSessionLayer
+/// or can be returned from a handler directly.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::PersistSession;
+///
+/// async fn login(session: Session<SessionPgPool>) -> (PersistSession, &'static str) {
+///     session.set("user-id", 1);
+///     (PersistSession, "logged in")
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PersistSession;
+
+impl IntoResponseParts for PersistSession {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+        Ok(res)
+    }
+}
+
+/// Layer that marks every response of the routes it wraps with PersistSession.
+///
+/// This must be within the SessionLayer, so add it to the routes themselves.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{PersistSessionLayer, SessionLayer};
+///
+/// let app = Router::new()
+///     .route("/account", post(update_account).layer(PersistSessionLayer))
+///     .route("/", get(index))
+///     .layer(SessionLayer::new(session_store));
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PersistSessionLayer;
+
+impl<S> Layer<S> for PersistSessionLayer {
+    type Service = PersistSessionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PersistSessionService { inner }
+    }
+}
+
+/// Service created by PersistSessionLayer.
+#[derive(Debug, Clone)]
+pub struct PersistSessionService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for PersistSessionService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let future = self.inner.call(req);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            response.extensions_mut().insert(PersistSession);
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,92 @@
+use axum_core::response::{IntoResponse, Response};
+use http::{header, request::Parts, HeaderValue, StatusCode};
+use std::fmt;
+
+/// The Rejection returned when a Session extractor fails.
+///
+/// Responds with a JSON body of `{"error": "...", "code": "..."}` when the request's
+/// Accept header allows JSON, otherwise the error is returned as plain text.
+#[derive(Debug, Clone)]
+pub struct SessionRejection {
+    status: StatusCode,
+    error: &'static str,
+    code: &'static str,
+    json: bool,
+}
+
+impl SessionRejection {
+    /// Rejection used when the SessionLayer did not add what was being extracted.
+    pub(crate) fn missing_layer(parts: &Parts) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
+            code: "session_layer_missing",
+            json: accepts_json(parts),
+        }
+    }
+
+    /// Rejection used when a TypedSession's stored data can not be deserialized into its type.
+    pub(crate) fn invalid_typed_session(parts: &Parts) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: "Can't deserialize the `TypedSession`'s data stored within the `Session`.",
+            code: "typed_session_invalid",
+            json: accepts_json(parts),
+        }
+    }
+
+    /// The status code the rejection responds with.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The error message.
+    pub fn error(&self) -> &'static str {
+        self.error
+    }
+
+    /// The machine readable error code.
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+}
+
+fn accepts_json(parts: &Parts) -> bool {
+    parts
+        .headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|media| media.split(';').next())
+        .map(|media| media.trim().to_ascii_lowercase())
+        .any(|media| media == "application/json" || media.ends_with("+json"))
+}
+
+impl fmt::Display for SessionRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.error)
+    }
+}
+
+impl std::error::Error for SessionRejection {}
+
+impl IntoResponse for SessionRejection {
+    fn into_response(self) -> Response {
+        if self.json {
+            let body = serde_json::json!({ "error": self.error, "code": self.code }).to_string();
+
+            (
+                self.status,
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                )],
+                body,
+            )
+                .into_response()
+        } else {
+            (self.status, self.error).into_response()
+        }
+    }
+}
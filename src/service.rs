@@ -1,4 +1,4 @@
-use crate::{DatabasePool, Session, SessionConfig, SessionData, SessionStore};
+use crate::{DatabasePool, PersistSession, Session, SessionConfig, SessionData, SessionStore};
 use axum_core::{
     body::{self, BoxBody},
     response::Response,
@@ -10,8 +10,8 @@ use cookie::{Cookie, CookieJar, Key};
 use futures::future::BoxFuture;
 use http::{
     self,
-    header::{COOKIE, SET_COOKIE},
-    HeaderMap, Request,
+    header::{CACHE_CONTROL, COOKIE, SET_COOKIE},
+    HeaderMap, HeaderValue, Request,
 };
 use http_body::Body as HttpBody;
 use std::{
@@ -26,6 +26,7 @@ use tower_service::Service;
 enum CookieType {
     Storable,
     Data,
+    Csrf,
 }
 
 impl CookieType {
@@ -34,6 +35,16 @@ impl CookieType {
         match self {
             CookieType::Data => config.cookie_name.to_string(),
             CookieType::Storable => config.storable_cookie_name.to_string(),
+            CookieType::Csrf => config.csrf_cookie_name.as_deref().unwrap_or("").to_string(),
+        }
+    }
+
+    /// The CSRF cookie must be readable by client side scripts.
+    #[inline]
+    pub(crate) fn http_only(&self, config: &SessionConfig) -> bool {
+        match self {
+            CookieType::Csrf => false,
+            _ => config.cookie_http_only,
         }
     }
 }
@@ -74,11 +85,11 @@ where
         let mut ready_inner = std::mem::replace(&mut self.inner, not_ready_inner);
 
         Box::pin(async move {
-            let cookies = get_cookies(&req);
+            let cookies = get_cookies(req.headers(), &store.config);
             let mut session = Session::new(&store, &cookies).await;
             let accepted = cookies
-                .get_cookie(&store.config.storable_cookie_name, &store.config.key)
-                .map_or(false, |c| c.value().parse().unwrap_or(false));
+                .get_cookie(&store.config.storable_cookie_name, &store.config)
+                .is_some_and(|c| c.value().parse().unwrap_or(false));
 
             // Check if the session id exists if not lets check if it exists in the database or generate a new session.
             if !store.service_session_data(&session) {
@@ -95,6 +106,7 @@ where
                     sess.autoremove = Utc::now() + store.config.memory_lifespan;
                 }
 
+                sess.last_accessed = Utc::now();
                 store.inner.insert(session.id.inner(), sess);
             }
 
@@ -107,17 +119,48 @@ where
             // let's check if any sessions expired. We don't want to hog memory
             // forever by abandoned sessions (e.g. when a client lost their cookie)
             // throttle by memory lifespan - e.g. sweep every hour
+            // Only the request that moves the timer forward sweeps, so concurrent requests
+            // don't all walk every session at once when the timer runs out.
             if last_sweep <= Utc::now() {
-                store.inner.retain(|_k, v| v.autoremove > Utc::now());
-                store.timers.write().await.last_expiry_sweep =
-                    Utc::now() + store.config.memory_lifespan;
+                let sweep = {
+                    let mut timers = store.timers.write().await;
+
+                    if timers.last_expiry_sweep <= Utc::now() {
+                        timers.last_expiry_sweep = Utc::now() + store.config.memory_lifespan;
+                        true
+                    } else {
+                        false
+                    }
+                };
+
+                if sweep {
+                    store.inner.retain(|_k, v| v.autoremove > Utc::now());
+                }
             }
 
             // Throttle by database lifespan - e.g. sweep every 6 hours
             if last_database_sweep <= Utc::now() && store.is_persistent() {
-                store.cleanup().await.unwrap();
-                store.timers.write().await.last_database_expiry_sweep =
-                    Utc::now() + store.config.lifespan;
+                let sweep = {
+                    let mut timers = store.timers.write().await;
+
+                    if timers.last_database_expiry_sweep <= Utc::now() {
+                        timers.last_database_expiry_sweep = Utc::now() + store.config.lifespan;
+                        true
+                    } else {
+                        false
+                    }
+                };
+
+                if sweep {
+                    store.cleanup().await.unwrap();
+                }
+            }
+
+            // Make sure the handler can read the CSRF token before the cookie is sent.
+            if store.config.csrf_cookie_name.is_some() {
+                if let Some(mut sess) = store.inner.get_mut(&session.id.inner()) {
+                    sess.csrf_token();
+                }
             }
 
             // Sets a clone of the Store in the Extensions for Direct usage and sets the Session for Direct usage
@@ -126,18 +169,19 @@ where
 
             let mut response = ready_inner.call(req).await?.map(body::boxed);
 
-            let (storable, renew, accepted) =
+            let (storable, renew, destroy, accepted) =
                 if let Some(session_data) = session.store.inner.get(&session.id.inner()) {
                     (
                         session_data.storable,
                         session_data.renew,
+                        session_data.destroy,
                         session_data.storable,
                     )
                 } else {
-                    (false, false, false)
+                    (false, false, false, false)
                 };
 
-            if renew {
+            if renew || destroy {
                 // Lets change the Session ID and destory the old Session from the database.
                 let session_id = Session::generate_uuid(&store).await;
 
@@ -145,7 +189,7 @@ where
                 if store.is_persistent() {
                     session
                         .store
-                        .destroy_session(&session.id.inner())
+                        .persist_destroy(&session.id.inner())
                         .await
                         .unwrap();
                 }
@@ -153,7 +197,9 @@ where
                 // Lets remove update and reinsert.
                 if let Some((_, mut session_data)) = session.store.inner.remove(&session.id.inner())
                 {
+                    // Destroy already cleared the data so anything left was set after it.
                     session_data.id = session_id.0;
+                    session_data.destroy = false;
                     session_data.renew = false;
                     session.id = session_id;
                     store.inner.insert(session.id.inner(), session_data);
@@ -166,30 +212,67 @@ where
             if store.config.session_mode.is_storable() && accepted
                 || !store.config.session_mode.is_storable()
             {
-                cookies.add_cookie(
+                cookies.add_cookie_variants(
                     create_cookie(&store.config, session.id.inner(), CookieType::Data),
                     &store.config.key,
+                    &store.config,
                 );
+
+                // The CSRF cookie is left unencrypted so client side scripts can read it.
+                if store.config.csrf_cookie_name.is_some() {
+                    let token = session
+                        .store
+                        .inner
+                        .get_mut(&session.id.inner())
+                        .map(|mut sess| sess.csrf_token());
+
+                    if let Some(token) = token {
+                        cookies.add_cookie_variants(
+                            create_cookie(&store.config, token, CookieType::Csrf),
+                            &None,
+                            &store.config,
+                        );
+                    }
+                }
             } else {
-                cookies.add_cookie(
+                cookies.add_cookie_variants(
                     remove_cookie(&store.config, CookieType::Data),
                     &store.config.key,
+                    &store.config,
                 );
+
+                if store.config.csrf_cookie_name.is_some() {
+                    cookies.add_cookie_variants(
+                        remove_cookie(&store.config, CookieType::Csrf),
+                        &None,
+                        &store.config,
+                    );
+                }
             }
 
             // Always Add the Storable Cookie so we can keep track if they can store the session.
-            cookies.add_cookie(
+            cookies.add_cookie_variants(
                 create_cookie(&store.config, storable.to_string(), CookieType::Storable),
                 &store.config.key,
+                &store.config,
             );
 
+            let marked = response.extensions().get::<PersistSession>().is_some();
+
             // Add the Session ID so it can link back to a Session if one exists.
             if (!store.config.session_mode.is_storable() || accepted) && store.is_persistent() {
                 let sess = if let Some(mut sess) = session.store.inner.get_mut(&session.id.inner())
                 {
-                    if store.config.always_save
+                    let persist = !store.config.persist_marked_only || marked || sess.persist;
+                    sess.persist = false;
+
+                    if !persist {
+                        // Unmarked routes keep changes in memory until a marked request saves them.
+                        None
+                    } else if store.config.always_save
                         || sess.update
                         || sess.expires - Utc::now() <= store.config.expiration_update
+                        || sess.last_accessed_stale(&store.config)
                     {
                         if sess.longterm {
                             sess.expires = Utc::now() + store.config.max_lifespan;
@@ -198,6 +281,7 @@ where
                         };
 
                         sess.update = false;
+                        sess.stored_last_accessed = sess.last_accessed;
                         Some(sess.clone())
                     } else {
                         None
@@ -207,7 +291,7 @@ where
                 };
 
                 if let Some(sess) = sess {
-                    session.store.store_session(&sess).await.unwrap()
+                    session.store.persist_session(&sess).await.unwrap()
                 }
             }
 
@@ -218,13 +302,20 @@ where
                 if store.is_persistent() {
                     session
                         .store
-                        .destroy_session(&session.id.inner())
+                        .persist_destroy(&session.id.inner())
                         .await
                         .unwrap();
                 }
             }
 
-            set_cookies(cookies, response.headers_mut());
+            // Some responses like streams or 304's should not carry cookies, the session was still saved above.
+            if !store
+                .config
+                .skips_cookies(response.status(), response.headers())
+                && set_cookies(cookies, response.headers_mut())
+            {
+                set_cache_control(&store.config, response.headers_mut());
+            }
 
             Ok(response)
         })
@@ -245,17 +336,32 @@ where
 }
 
 pub(crate) trait CookiesExt {
-    fn get_cookie(&self, name: &str, key: &Option<Key>) -> Option<Cookie<'static>>;
+    fn get_cookie(&self, name: &str, config: &SessionConfig) -> Option<Cookie<'static>>;
     fn add_cookie(&mut self, cookie: Cookie<'static>, key: &Option<Key>);
+    fn add_cookie_variants(
+        &mut self,
+        cookie: Cookie<'static>,
+        key: &Option<Key>,
+        config: &SessionConfig,
+    );
 }
 
 impl CookiesExt for CookieJar {
-    fn get_cookie(&self, name: &str, key: &Option<Key>) -> Option<Cookie<'static>> {
-        if let Some(key) = key {
-            self.private(key).get(name)
-        } else {
-            self.get(name).cloned()
-        }
+    fn get_cookie(&self, name: &str, config: &SessionConfig) -> Option<Cookie<'static>> {
+        // Accept whichever of the cookie's variants the client sent back.
+        let cookie = std::iter::once(name.to_owned())
+            .chain(
+                config
+                    .cookie_variants
+                    .iter()
+                    .map(|variant| variant.name(name)),
+            )
+            .find_map(|name| get_variant(self, &name, config));
+
+        cookie.map(|mut cookie| {
+            cookie.set_name(name.to_owned());
+            cookie
+        })
     }
 
     fn add_cookie(&mut self, cookie: Cookie<'static>, key: &Option<Key>) {
@@ -265,13 +371,37 @@ impl CookiesExt for CookieJar {
             self.add(cookie)
         }
     }
+
+    fn add_cookie_variants(
+        &mut self,
+        cookie: Cookie<'static>,
+        key: &Option<Key>,
+        config: &SessionConfig,
+    ) {
+        for variant in &config.cookie_variants {
+            let mut variant_cookie = cookie.clone();
+            variant_cookie.set_name(variant.name(cookie.name()));
+            variant_cookie.set_same_site(variant.same_site);
+            self.add_cookie(variant_cookie, key);
+        }
+
+        self.add_cookie(cookie, key);
+    }
+}
+
+fn get_variant(jar: &CookieJar, name: &str, config: &SessionConfig) -> Option<Cookie<'static>> {
+    if let Some(key) = &config.key {
+        jar.private(key).get(name)
+    } else {
+        jar.get(name).cloned()
+    }
 }
 
 fn create_cookie<'a>(config: &SessionConfig, value: String, cookie_type: CookieType) -> Cookie<'a> {
     let mut cookie_builder = Cookie::build(cookie_type.get_name(config), value)
         .path(config.cookie_path.clone())
         .secure(config.cookie_secure)
-        .http_only(config.cookie_http_only)
+        .http_only(cookie_type.http_only(config))
         .same_site(config.cookie_same_site);
 
     if let Some(domain) = &config.cookie_domain {
@@ -290,7 +420,7 @@ fn create_cookie<'a>(config: &SessionConfig, value: String, cookie_type: CookieT
 fn remove_cookie<'a>(config: &SessionConfig, cookie_type: CookieType) -> Cookie<'a> {
     let mut cookie_builder = Cookie::build(cookie_type.get_name(config), "")
         .path(config.cookie_path.clone())
-        .http_only(config.cookie_http_only);
+        .http_only(cookie_type.http_only(config));
 
     if let Some(domain) = &config.cookie_domain {
         cookie_builder = cookie_builder.domain(domain.clone());
@@ -305,15 +435,25 @@ fn remove_cookie<'a>(config: &SessionConfig, cookie_type: CookieType) -> Cookie<
     cookie
 }
 
-fn get_cookies<ReqBody>(req: &Request<ReqBody>) -> CookieJar {
+pub(crate) fn get_cookies(headers: &HeaderMap, config: &SessionConfig) -> CookieJar {
     let mut jar = CookieJar::new();
 
-    let cookie_iter = req
-        .headers()
+    let cookie_iter = headers
         .get_all(COOKIE)
         .into_iter()
         .filter_map(|value| value.to_str().ok())
         .flat_map(|value| value.split(';'))
+        .filter(|cookie| {
+            // Discard attacker sized values before we spend any time parsing them.
+            let (name, value) = cookie.split_once('=').unwrap_or((cookie, ""));
+
+            if value.len() > config.cookie_max_value_length {
+                tracing::warn!("Discarding oversized cookie {}", name.trim());
+                false
+            } else {
+                true
+            }
+        })
         .filter_map(|cookie| Cookie::parse_encoded(cookie.to_owned()).ok());
 
     for cookie in cookie_iter {
@@ -323,10 +463,56 @@ fn get_cookies<ReqBody>(req: &Request<ReqBody>) -> CookieJar {
     jar
 }
 
-fn set_cookies(jar: CookieJar, headers: &mut HeaderMap) {
+fn set_cookies(jar: CookieJar, headers: &mut HeaderMap) -> bool {
+    let mut is_set = false;
+
     for cookie in jar.delta() {
         if let Ok(header_value) = cookie.encoded().to_string().parse() {
             headers.append(SET_COOKIE, header_value);
+            is_set = true;
+        }
+    }
+
+    is_set
+}
+
+fn set_cache_control(config: &SessionConfig, headers: &mut HeaderMap) {
+    if let Some(cache_control) = &config.cache_control {
+        // The handler may have set several Cache-Control headers, which together form one list of directives.
+        let mut values = Vec::new();
+
+        for existing in headers.get_all(CACHE_CONTROL) {
+            match existing.to_str() {
+                Ok(existing) if !existing.trim().is_empty() => values.push(existing.trim()),
+                Ok(_) => {}
+                // A value that is not visible ASCII can not be safely appended to, so it is left as the handler set it.
+                Err(_) => return,
+            }
+        }
+
+        let existing = values.join(", ");
+
+        let value = match existing.as_str() {
+            "" => cache_control.to_string(),
+            existing => {
+                // Leave the handler's choice alone if it already said how shared caches should behave.
+                let decided = existing.split(',').any(|directive| {
+                    let directive = directive.trim();
+                    directive.eq_ignore_ascii_case("private")
+                        || directive.eq_ignore_ascii_case("public")
+                        || directive.eq_ignore_ascii_case("no-store")
+                });
+
+                if decided {
+                    return;
+                }
+
+                format!("{}, {}", existing, cache_control)
+            }
+        };
+
+        if let Ok(header_value) = HeaderValue::from_str(&value) {
+            headers.insert(CACHE_CONTROL, header_value);
         }
     }
 }
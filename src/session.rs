@@ -1,8 +1,11 @@
-use crate::{CookiesExt, DatabasePool, SessionID, SessionStore};
+use crate::{
+    get_cookies, CookiesExt, DatabasePool, SessionConfig, SessionDataView, SessionID,
+    SessionRejection, SessionStore, SessionVersioned, VersionedValue,
+};
 use async_trait::async_trait;
 use axum_core::extract::FromRequestParts;
 use cookie::CookieJar;
-use http::{self, request::Parts, StatusCode};
+use http::{self, request::Parts};
 use serde::Serialize;
 use std::{
     convert::From,
@@ -34,13 +37,80 @@ where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
     S: Send + Sync,
 {
-    type Rejection = (http::StatusCode, &'static str);
+    type Rejection = SessionRejection;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        parts.extensions.get::<Session<T>>().cloned().ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
-        ))
+        parts
+            .extensions
+            .get::<Session<T>>()
+            .cloned()
+            .ok_or_else(|| SessionRejection::missing_layer(parts))
+    }
+}
+
+/// Reads the Session's UUID from the request's cookies without loading the Session.
+///
+/// This decrypts the cookie using the config's Key when one is set but never touches the SessionStore,
+/// so it can be used for logging within layers that run before the SessionLayer.
+/// Returns None if there is no valid Session cookie. The UUID is not checked to exist.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{peek_session_id, SessionConfig};
+///
+/// let config = SessionConfig::default();
+/// let (parts, body) = request.into_parts();
+/// tracing::info!("session {:?}", peek_session_id(&parts, &config));
+/// ```
+///
+pub fn peek_session_id(parts: &Parts, config: &SessionConfig) -> Option<Uuid> {
+    session_id(&get_cookies(&parts.headers, config), config)
+}
+
+fn session_id(cookies: &CookieJar, config: &SessionConfig) -> Option<Uuid> {
+    cookies
+        .get_cookie(&config.cookie_name, config)
+        .and_then(|c| Uuid::parse_str(c.value()).ok())
+}
+
+/// Gives access to the Session stored within a Request's extensions.
+///
+/// Useful within tower middleware layered inside of the SessionLayer where
+/// Axum's extractors are not available.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{SessionExt, SessionPgPool};
+///
+/// if let Some(session) = request.session::<SessionPgPool>() {
+///     let id: Option<i64> = session.get("user-id");
+/// }
+/// ```
+///
+pub trait SessionExt {
+    /// Returns the Session if the SessionLayer has added one.
+    fn session<T>(&self) -> Option<&Session<T>>
+    where
+        T: DatabasePool + Clone + Debug + Sync + Send + 'static;
+}
+
+impl<B> SessionExt for http::Request<B> {
+    #[inline]
+    fn session<T>(&self) -> Option<&Session<T>>
+    where
+        T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    {
+        self.extensions().get::<Session<T>>()
+    }
+}
+
+impl SessionExt for Parts {
+    #[inline]
+    fn session<T>(&self) -> Option<&Session<T>>
+    where
+        T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    {
+        self.extensions.get::<Session<T>>()
     }
 }
 
@@ -49,11 +119,7 @@ where
     S: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
     pub(crate) async fn new(store: &SessionStore<S>, cookies: &CookieJar) -> Self {
-        let value = cookies
-            .get_cookie(&store.config.cookie_name, &store.config.key)
-            .and_then(|c| Uuid::parse_str(c.value()).ok());
-
-        let id = match value {
+        let id = match session_id(cookies, &store.config) {
             Some(v) => SessionID(v),
             None => Self::generate_uuid(store).await,
         };
@@ -102,8 +168,31 @@ where
         self.store.renew(self.id.inner());
     }
 
+    /// Marks that the user's privileges just changed, such as after logging in or a role change.
+    ///
+    /// Unless disabled with SessionConfig::with_renew_on_privilege_change this renews the Session ID
+    /// at the end of the request while keeping the Session's data, preventing session fixation.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set("user-id", user.id);
+    /// session.mark_privilege_change();
+    /// ```
+    ///
+    #[inline]
+    pub fn mark_privilege_change(&self) {
+        if self.store.config.renew_on_privilege_change {
+            self.store.renew(self.id.inner());
+        }
+    }
+
     /// Sets the Current Session to be Destroyed on the next run.
     ///
+    /// The Session's data is cleared right away. Anything set or removed afterwards within
+    /// the same request applies to the new Session that replaces it.
+    /// The old Session's database data is deleted, or marked as deleted when soft delete is enabled,
+    /// and the client is given a new Session ID.
+    ///
     /// # Examples
     /// ```rust ignore
     /// session.destroy();
@@ -141,6 +230,21 @@ where
         self.store.set_store(self.id.inner(), storable);
     }
 
+    /// Marks the Session to be saved to the database at the end of this request.
+    ///
+    /// Only needed when SessionConfig::with_persist_marked_only is enabled
+    /// and the route is not already using PersistSessionLayer.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.persist();
+    /// ```
+    ///
+    #[inline]
+    pub fn persist(&self) {
+        self.store.persist(self.id.inner());
+    }
+
     /// Gets data from the Session's HashMap
     ///
     /// Provides an Option<T> that returns the requested data from the Sessions store.
@@ -158,6 +262,38 @@ where
         self.store.get(self.id.inner(), key)
     }
 
+    /// Gets a value stored with set_versioned from the Session's HashMap.
+    ///
+    /// Returns None if the Key does not exist. If the stored value is from another version
+    /// or can no longer be deserialized it is migrated or replaced by the type's fallback.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let theme: Option<Theme> = session.get_versioned("theme");
+    /// ```
+    ///
+    #[inline]
+    pub fn get_versioned<T: SessionVersioned>(&self, key: &str) -> Option<T> {
+        self.store
+            .get::<serde_json::Value>(self.id.inner(), key)
+            .map(VersionedValue::decode)
+    }
+
+    /// Takes a read-only snapshot of the Session's data and flags.
+    ///
+    /// Useful for logging or checking several values at once.
+    /// Returns None if the Session's data is unexpectedly missing.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let longterm = session.view().is_some_and(|view| view.longterm());
+    /// ```
+    ///
+    #[inline]
+    pub fn view(&self) -> Option<SessionDataView> {
+        self.store.view(self.id.inner())
+    }
+
     /// Removes a Key from the Current Session's HashMap returning it.
     ///
     /// Provides an Option<T> that returns the requested data from the Sessions store.
@@ -177,6 +313,9 @@ where
 
     /// Sets data to the Current Session's HashMap.
     ///
+    /// A value that serializes to null, like None, removes the Key instead of being stored
+    /// when SessionConfig::with_null_as_remove is set.
+    ///
     /// # Examples
     /// ```rust ignore
     /// session.set("user-id", 1);
@@ -187,6 +326,19 @@ where
         self.store.set(self.id.inner(), key, value);
     }
 
+    /// Sets a value to the Current Session's HashMap along with its type's version.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_versioned("theme", Theme::Dark);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_versioned<T: SessionVersioned>(&self, key: &str, value: T) {
+        self.store
+            .set(self.id.inner(), key, VersionedValue::new(value));
+    }
+
     /// Removes a Key from the Current Session's HashMap.
     /// Does not process the String into a Type, Just removes it.
     ///
@@ -200,7 +352,7 @@ where
         self.store.remove(self.id.inner(), key);
     }
 
-    /// Clears all data from the Current Session's HashMap.
+    /// Clears all data from the Current Session's HashMap except for pinned keys.
     ///
     /// # Examples
     /// ```rust ignore
@@ -212,6 +364,68 @@ where
         self.store.clear_session_data(self.id.inner());
     }
 
+    /// Gets the Session's CSRF token sent within the CSRF cookie.
+    ///
+    /// Returns None if SessionConfig::with_csrf_cookie was not set.
+    /// The token changes when the Session is renewed or destroyed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session.csrf_token();
+    /// ```
+    ///
+    #[inline]
+    pub fn csrf_token(&self) -> Option<String> {
+        self.store.csrf_token(self.id.inner())
+    }
+
+    /// Checks a value sent back by the client against the Session's CSRF token.
+    ///
+    /// Use this with the value of a header or form field that client side scripts copied from the CSRF cookie.
+    /// The comparison is done in constant time. Returns false if the Session has no CSRF token.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let header = headers.get("x-csrf-token").and_then(|v| v.to_str().ok()).unwrap_or("");
+    ///
+    /// if !session.verify_double_submit(header) {
+    ///     return StatusCode::FORBIDDEN;
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn verify_double_submit(&self, value: &str) -> bool {
+        self.store.verify_double_submit(self.id.inner(), value)
+    }
+
+    /// Pins a Key so it is kept when the Session is cleared.
+    /// Useful for keeping device or consent data when logging a user out.
+    /// Pinned keys are saved with the Session. Destroying the Session still removes them.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.pin("device-id");
+    /// session.clear();
+    /// let device: Option<String> = session.get("device-id");
+    /// ```
+    ///
+    #[inline]
+    pub fn pin(&self, key: &str) {
+        self.store.pin(self.id.inner(), key);
+    }
+
+    /// Unpins a Key so it is removed when the Session is cleared.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.unpin("device-id");
+    /// ```
+    ///
+    #[inline]
+    pub fn unpin(&self, key: &str) {
+        self.store.unpin(self.id.inner(), key);
+    }
+
     /// Returns a i64 count of how many Sessions exist.
     ///
     /// If the Session is persistant it will return all sessions within the database.
@@ -258,13 +472,14 @@ where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
     S: Send + Sync,
 {
-    type Rejection = (http::StatusCode, &'static str);
+    type Rejection = SessionRejection;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let session = parts.extensions.get::<Session<T>>().cloned().ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
-        ))?;
+        let session = parts
+            .extensions
+            .get::<Session<T>>()
+            .cloned()
+            .ok_or_else(|| SessionRejection::missing_layer(parts))?;
 
         Ok(session.into())
     }
@@ -291,6 +506,38 @@ where
         self.store.get(self.id.inner(), key)
     }
 
+    /// Gets a value stored with set_versioned from the Session's HashMap.
+    ///
+    /// Returns None if the Key does not exist. If the stored value is from another version
+    /// or can no longer be deserialized it is migrated or replaced by the type's fallback.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let theme: Option<Theme> = session.get_versioned("theme");
+    /// ```
+    ///
+    #[inline]
+    pub fn get_versioned<T: SessionVersioned>(&self, key: &str) -> Option<T> {
+        self.store
+            .get::<serde_json::Value>(self.id.inner(), key)
+            .map(VersionedValue::decode)
+    }
+
+    /// Takes a read-only snapshot of the Session's data and flags.
+    ///
+    /// Useful for logging or checking several values at once.
+    /// Returns None if the Session's data is unexpectedly missing.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let longterm = session.view().is_some_and(|view| view.longterm());
+    /// ```
+    ///
+    #[inline]
+    pub fn view(&self) -> Option<SessionDataView> {
+        self.store.view(self.id.inner())
+    }
+
     /// Returns a i64 count of how many Sessions exist.
     ///
     /// If the Session is persistant it will return all sessions within the database.
@@ -1,8 +1,10 @@
 use crate::SessionConfig;
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{self, Display, Formatter},
 };
 use uuid::Uuid;
@@ -23,13 +25,27 @@ use uuid::Uuid;
 pub struct SessionData {
     pub(crate) id: Uuid,
     pub(crate) data: HashMap<String, String>,
+    /// Keys that are kept when the Session is cleared.
+    #[serde(default)]
+    pub(crate) pinned: HashSet<String>,
+    /// Token sent within the CSRF cookie when it is enabled.
+    #[serde(default)]
+    pub(crate) csrf_token: Option<String>,
     pub(crate) expires: DateTime<Utc>,
     pub(crate) autoremove: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub(crate) last_accessed: DateTime<Utc>,
+    /// The last_accessed time that was last saved to the database.
+    #[serde(skip)]
+    pub(crate) stored_last_accessed: DateTime<Utc>,
     pub(crate) destroy: bool,
     pub(crate) renew: bool,
     pub(crate) longterm: bool,
     pub(crate) storable: bool,
     pub(crate) update: bool,
+    /// Set when the current request asked for the Session to be saved to the database.
+    #[serde(skip)]
+    pub(crate) persist: bool,
 }
 
 impl SessionData {
@@ -50,13 +66,18 @@ impl SessionData {
         Self {
             id,
             data: HashMap::new(),
+            pinned: HashSet::new(),
+            csrf_token: None,
             expires: Utc::now() + config.lifespan,
             destroy: false,
             renew: false,
             autoremove: Utc::now() + config.memory_lifespan,
+            last_accessed: Utc::now(),
+            stored_last_accessed: Utc::now(),
             longterm: false,
             storable,
             update: true,
+            persist: false,
         }
     }
 
@@ -78,6 +99,67 @@ impl SessionData {
         self.expires >= Utc::now()
     }
 
+    /// Checks if last_accessed has moved far enough past the saved value to need saving again.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionConfig, SessionData};
+    /// use uuid::Uuid;
+    ///
+    /// let config = SessionConfig::default();
+    /// let token = Uuid::new_v4();
+    /// let session_data = SessionData::new(token, true, &config);
+    /// let stale = session_data.last_accessed_stale(&config);
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn last_accessed_stale(&self, config: &SessionConfig) -> bool {
+        self.last_accessed - self.stored_last_accessed >= config.last_accessed_resolution
+    }
+
+    /// Gets the Session's CSRF token generating a new one if it has none.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session_data.csrf_token();
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn csrf_token(&mut self) -> String {
+        if let Some(token) = &self.csrf_token {
+            return token.clone();
+        }
+
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+        self.csrf_token = Some(token.clone());
+        self.update = true;
+        token
+    }
+
+    /// Checks a double-submitted value against the Session's CSRF token in constant time.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let valid = session_data.verify_double_submit("token");
+    /// ```
+    ///
+    #[inline]
+    pub(crate) fn verify_double_submit(&self, value: &str) -> bool {
+        match &self.csrf_token {
+            Some(token) if token.len() == value.len() => {
+                token
+                    .bytes()
+                    .zip(value.bytes())
+                    .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+            }
+            _ => false,
+        }
+    }
+
     /// Sets the Session to renew its Session ID.
     /// This Deletes Session data from the database
     /// associated with the old key. This helps to enhance
@@ -90,12 +172,16 @@ impl SessionData {
     ///
     #[inline]
     pub fn renew(&mut self) {
+        self.csrf_token = None;
         self.renew = true;
         self.update = true;
     }
 
     /// Sets the Current Session to be Destroyed on the next run.
     ///
+    /// The Session's data is cleared right away so anything set or removed afterwards
+    /// applies to the new Session that replaces it.
+    ///
     /// # Examples
     /// ```rust ignore
     /// session.destroy();
@@ -103,6 +189,10 @@ impl SessionData {
     ///
     #[inline]
     pub fn destroy(&mut self) {
+        self.data.clear();
+        self.pinned.clear();
+        self.csrf_token = None;
+        self.longterm = false;
         self.destroy = true;
         self.update = true;
     }
@@ -174,21 +264,33 @@ impl SessionData {
     }
 
     /// Sets data to the Current Session's HashMap.
+    /// The Session is only marked to be saved if the value changed.
+    /// Values that serialize to null remove the Key instead when config.null_as_remove is set.
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.set("user-id", 1);
+    /// session.set("user-id", 1, &config);
     /// ```
     ///
     #[inline]
-    pub fn set(&mut self, key: &str, value: impl Serialize) {
+    pub fn set(&mut self, key: &str, value: impl Serialize, config: &SessionConfig) {
         let value = serde_json::to_string(&value).unwrap_or_else(|_| "".to_string());
-        let _ = self.data.insert(key.to_string(), value);
-        self.update = true;
+
+        if config.null_as_remove && value == "null" {
+            self.remove(key);
+            return;
+        }
+
+        // Only mark the Session to be saved when the value actually changed.
+        if self.data.get(key) != Some(&value) {
+            let _ = self.data.insert(key.to_string(), value);
+            self.update = true;
+        }
     }
 
     /// Removes a Key from the Current Session's HashMap.
     /// Does not process the String into a Type, Just removes it.
+    /// The Session is only marked to be saved if the Key existed.
     ///
     /// # Examples
     /// ```rust ignore
@@ -197,11 +299,12 @@ impl SessionData {
     ///
     #[inline]
     pub fn remove(&mut self, key: &str) {
-        let _ = self.data.remove(key);
-        self.update = true;
+        if self.data.remove(key).is_some() {
+            self.update = true;
+        }
     }
 
-    /// Clears all data from the Current Session's HashMap.
+    /// Clears all data from the Current Session's HashMap except for pinned keys.
     ///
     /// # Examples
     /// ```rust ignore
@@ -210,9 +313,117 @@ impl SessionData {
     ///
     #[inline]
     pub fn clear(&mut self) {
-        self.data.clear();
+        let pinned = &self.pinned;
+        self.data.retain(|key, _| pinned.contains(key));
         self.update = true;
     }
+
+    /// Pins a Key so it is kept when the Session is cleared.
+    /// The Key does not need to exist yet.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.pin("device-id");
+    /// ```
+    ///
+    #[inline]
+    pub fn pin(&mut self, key: &str) {
+        if self.pinned.insert(key.to_string()) {
+            self.update = true;
+        }
+    }
+
+    /// Unpins a Key so it is removed when the Session is cleared.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.unpin("device-id");
+    /// ```
+    ///
+    #[inline]
+    pub fn unpin(&mut self, key: &str) {
+        if self.pinned.remove(key) {
+            self.update = true;
+        }
+    }
+}
+
+/// A read-only snapshot of a Session's data and flags.
+///
+/// Changes made to the Session after the snapshot was taken are not reflected within it.
+///
+/// # Examples
+/// ```rust ignore
+/// if let Some(view) = session.view() {
+///     tracing::info!("session {} has {} keys", view.id(), view.data().len());
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionDataView {
+    id: Uuid,
+    data: HashMap<String, String>,
+    pinned: HashSet<String>,
+    expires: DateTime<Utc>,
+    last_accessed: DateTime<Utc>,
+    longterm: bool,
+    storable: bool,
+}
+
+impl SessionDataView {
+    /// The Session's UUID.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// All of the Session's keys and their serialized values.
+    pub fn data(&self) -> &HashMap<String, String> {
+        &self.data
+    }
+
+    /// Deserializes a value from the snapshot. Returns None if the Key does not exist or fails to deserialize.
+    pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        serde_json::from_str(self.data.get(key)?).ok()
+    }
+
+    /// Keys that are kept when the Session is cleared.
+    pub fn pinned(&self) -> &HashSet<String> {
+        &self.pinned
+    }
+
+    /// When the Session expires within the database.
+    pub fn expires(&self) -> DateTime<Utc> {
+        self.expires
+    }
+
+    /// When the Session was last requested.
+    pub fn last_accessed(&self) -> DateTime<Utc> {
+        self.last_accessed
+    }
+
+    /// Whether the Session uses the long term expiration.
+    pub fn longterm(&self) -> bool {
+        self.longterm
+    }
+
+    /// Whether the Session is allowed to be stored.
+    pub fn storable(&self) -> bool {
+        self.storable
+    }
+}
+
+impl From<&SessionData> for SessionDataView {
+    fn from(session: &SessionData) -> Self {
+        Self {
+            id: session.id,
+            data: session.data.clone(),
+            pinned: session.pinned.clone(),
+            expires: session.expires,
+            last_accessed: session.last_accessed,
+            longterm: session.longterm,
+            storable: session.storable,
+        }
+    }
 }
 
 /// Contains the UUID the Session.
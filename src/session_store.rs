@@ -1,9 +1,12 @@
-use crate::{DatabasePool, Session, SessionConfig, SessionData, SessionError, SessionTimers};
+use crate::{
+    DatabasePool, Session, SessionConfig, SessionData, SessionDataView, SessionError,
+    SessionRejection, SessionTimers, SessionWrite, SessionWriter,
+};
 use async_trait::async_trait;
 use axum_core::extract::FromRequestParts;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use dashmap::DashMap;
-use http::{self, request::Parts, StatusCode};
+use http::{self, request::Parts};
 use serde::Serialize;
 use std::{
     fmt::Debug,
@@ -12,6 +15,76 @@ use std::{
 };
 use tokio::sync::RwLock;
 
+/// A summary of a SessionStore's effective configuration returned by SessionStore::describe.
+///
+/// Durations are in seconds. The encryption Key itself is never included.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStoreInfo {
+    /// The type name of the store's DatabasePool.
+    pub backend: &'static str,
+    /// Whether sessions are saved to the database.
+    pub persistent: bool,
+    /// The database table sessions are saved within.
+    pub table_name: String,
+    /// Storable or Always.
+    pub session_mode: &'static str,
+    /// Whether sessions are saved on every request even when unchanged.
+    pub always_save: bool,
+    /// Whether the pool marks destroyed and expired sessions as deleted rather than removing them.
+    /// Always false without a pool.
+    pub soft_delete: bool,
+    /// Whether each session's database writes go through a single writer task.
+    pub single_writer: bool,
+    /// Whether sessions are only saved on requests marked with PersistSession.
+    pub persist_marked_only: bool,
+    /// Whether setting a value that serializes to null removes the key.
+    pub null_as_remove: bool,
+    /// Whether Session::mark_privilege_change renews the session ID.
+    pub renew_on_privilege_change: bool,
+    /// The name of the cookie holding the session ID.
+    pub cookie_name: String,
+    /// The name of the cookie holding whether the session may be stored.
+    pub storable_cookie_name: String,
+    /// The domain cookies are set for, if any.
+    pub cookie_domain: Option<String>,
+    /// The path cookies are set for.
+    pub cookie_path: String,
+    /// The SameSite attribute of the cookies.
+    pub cookie_same_site: String,
+    /// Whether cookies are only sent over https.
+    pub cookie_secure: bool,
+    /// Whether cookies are hidden from javascript.
+    pub cookie_http_only: bool,
+    /// How long cookies last within the browser, or None for the browser session.
+    pub cookie_max_age: Option<i64>,
+    /// The longest cookie value read from requests, in bytes.
+    pub cookie_max_value_length: usize,
+    /// The names of the session cookie's variants.
+    pub cookie_variants: Vec<String>,
+    /// The status codes of responses that are never given cookies.
+    pub cookie_skip_statuses: Vec<u16>,
+    /// The content types of responses that are never given cookies.
+    pub cookie_skip_content_types: Vec<String>,
+    /// The Cache-Control value added to responses that set cookies, if any.
+    pub cache_control: Option<String>,
+    /// The name of the double-submit CSRF cookie, if enabled.
+    pub csrf_cookie_name: Option<String>,
+    /// Whether cookies are encrypted using a Key.
+    pub encrypted_cookies: bool,
+    /// Whether a Key was required when the store was created.
+    pub require_key: bool,
+    /// How long a session lasts within the database.
+    pub lifespan: i64,
+    /// How long a longterm session lasts within the database.
+    pub max_lifespan: i64,
+    /// How long an unused session is kept within memory.
+    pub memory_lifespan: i64,
+    /// How close to expiring a session must be before a request extends it.
+    pub expiration_update: i64,
+    /// How far last_accessed must move before it alone causes a save.
+    pub last_accessed_resolution: i64,
+}
+
 /// Contains the main Services storage for all session's and database access for persistant Sessions.
 ///
 /// # Examples
@@ -35,6 +108,8 @@ where
     pub config: SessionConfig,
     //move this to creation on layer.
     pub(crate) timers: Arc<RwLock<SessionTimers>>,
+    /// per session writers used when single_writer is enabled.
+    pub(crate) writers: Arc<DashMap<String, SessionWriter>>,
 }
 
 #[async_trait]
@@ -43,13 +118,14 @@ where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
     S: Send + Sync,
 {
-    type Rejection = (http::StatusCode, &'static str);
+    type Rejection = SessionRejection;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        parts.extensions.get::<SessionStore<T>>().cloned().ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
-        ))
+        parts
+            .extensions
+            .get::<SessionStore<T>>()
+            .cloned()
+            .ok_or_else(|| SessionRejection::missing_layer(parts))
     }
 }
 
@@ -59,6 +135,10 @@ where
 {
     /// Constructs a New SessionStore.
     ///
+    /// # Panics
+    /// Panics if the config requires a Key but none was set or disagrees with the pool on soft delete.
+    /// Use try_new to handle these as an error.
+    ///
     /// # Examples
     /// ```rust
     /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
@@ -69,17 +149,57 @@ where
     ///
     #[inline]
     pub fn new(client: Option<T>, config: SessionConfig) -> Self {
-        Self {
+        match Self::try_new(client, config) {
+            Ok(store) => store,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Constructs a New SessionStore, checking the config first.
+    ///
+    /// # Errors
+    /// - ['SessionError::ConfigError'] is returned if the config requires a Key but none was set.
+    /// - ['SessionError::ConfigError'] is returned if the config and the pool disagree on soft delete,
+    ///   as the pool would otherwise remove sessions that should be kept or the other way around.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{Key, SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default().with_key(Key::generate()).with_require_key(true);
+    /// let session_store = SessionStore::<SessionNullPool>::try_new(None, config).unwrap();
+    /// ```
+    ///
+    #[inline]
+    pub fn try_new(client: Option<T>, config: SessionConfig) -> Result<Self, SessionError> {
+        if config.require_key && config.key.is_none() {
+            return Err(SessionError::ConfigError(
+                "a Key is required but none was set, cookies would not be encrypted".to_owned(),
+            ));
+        }
+
+        if let Some(client) = &client {
+            if client.soft_delete() != config.soft_delete {
+                return Err(SessionError::ConfigError(format!(
+                    "soft delete is {} within the SessionConfig but {} within the pool, set both with with_soft_delete",
+                    enabled(config.soft_delete),
+                    enabled(client.soft_delete()),
+                )));
+            }
+        }
+
+        Ok(Self {
             client,
             inner: Default::default(),
-            config,
             timers: Arc::new(RwLock::new(SessionTimers {
                 // the first expiry sweep is scheduled one lifetime from start-up
-                last_expiry_sweep: Utc::now() + Duration::hours(1),
+                last_expiry_sweep: Utc::now() + config.memory_lifespan,
                 // the first expiry sweep is scheduled one lifetime from start-up
-                last_database_expiry_sweep: Utc::now() + Duration::hours(6),
+                last_database_expiry_sweep: Utc::now() + config.lifespan,
             })),
-        }
+            config,
+            writers: Default::default(),
+        })
     }
 
     /// Checks if the database is in persistent mode.
@@ -100,6 +220,85 @@ where
         self.client.is_some()
     }
 
+    /// Describes the store's effective configuration.
+    ///
+    /// Useful to return from a debug endpoint to check what the store is actually using.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config);
+    /// let info = session_store.describe();
+    /// ```
+    ///
+    pub fn describe(&self) -> SessionStoreInfo {
+        let config = &self.config;
+
+        SessionStoreInfo {
+            backend: std::any::type_name::<T>(),
+            persistent: self.is_persistent(),
+            table_name: config.table_name.to_string(),
+            session_mode: if config.session_mode.is_storable() {
+                "Storable"
+            } else {
+                "Always"
+            },
+            always_save: config.always_save,
+            soft_delete: self
+                .client
+                .as_ref()
+                .is_some_and(|client| client.soft_delete()),
+            single_writer: config.single_writer,
+            persist_marked_only: config.persist_marked_only,
+            null_as_remove: config.null_as_remove,
+            renew_on_privilege_change: config.renew_on_privilege_change,
+            cookie_name: config.cookie_name.to_string(),
+            storable_cookie_name: config.storable_cookie_name.to_string(),
+            cookie_domain: config
+                .cookie_domain
+                .as_ref()
+                .map(|domain| domain.to_string()),
+            cookie_path: config.cookie_path.to_string(),
+            cookie_same_site: format!("{:?}", config.cookie_same_site),
+            cookie_secure: config.cookie_secure,
+            cookie_http_only: config.cookie_http_only,
+            cookie_max_age: config.cookie_max_age.map(|max_age| max_age.num_seconds()),
+            cookie_max_value_length: config.cookie_max_value_length,
+            cookie_variants: config
+                .cookie_variants
+                .iter()
+                .map(|variant| variant.name(&config.cookie_name))
+                .collect(),
+            cookie_skip_statuses: config
+                .cookie_skip_statuses
+                .iter()
+                .map(|status| status.as_u16())
+                .collect(),
+            cookie_skip_content_types: config
+                .cookie_skip_content_types
+                .iter()
+                .map(|content_type| content_type.to_string())
+                .collect(),
+            cache_control: config
+                .cache_control
+                .as_ref()
+                .map(|cache_control| cache_control.to_string()),
+            csrf_cookie_name: config
+                .csrf_cookie_name
+                .as_ref()
+                .map(|name| name.to_string()),
+            encrypted_cookies: config.key.is_some(),
+            require_key: config.require_key,
+            lifespan: config.lifespan.num_seconds(),
+            max_lifespan: config.max_lifespan.num_seconds(),
+            memory_lifespan: config.memory_lifespan.num_seconds(),
+            expiration_update: config.expiration_update.num_seconds(),
+            last_accessed_resolution: config.last_accessed_resolution.num_seconds(),
+        }
+    }
+
     /// Creates the Database Table needed for the Session if it does not exist.
     ///
     /// If client is None it will return Ok(()).
@@ -148,7 +347,47 @@ where
     #[inline]
     pub async fn cleanup(&self) -> Result<(), SessionError> {
         if let Some(client) = &self.client {
-            client.delete_by_expiry(&self.config.table_name).await?;
+            if self.config.soft_delete {
+                client
+                    .soft_delete_by_expiry(&self.config.table_name)
+                    .await?;
+            } else {
+                client.delete_by_expiry(&self.config.table_name).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes sessions that were marked as deleted longer ago than the given duration from the Database.
+    ///
+    /// This is only needed when soft delete is enabled and should be run periodically.
+    /// If client is None it will return Ok(()).
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_soft_delete(true);
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config);
+    /// async {
+    ///     let _ = session_store.purge(Duration::days(30)).await.unwrap();
+    /// };
+    /// ```
+    ///
+    #[inline]
+    pub async fn purge(&self, older_than: Duration) -> Result<(), SessionError> {
+        if let Some(client) = &self.client {
+            client
+                .purge_deleted(
+                    (Utc::now() - older_than).timestamp(),
+                    &self.config.table_name,
+                )
+                .await?;
         }
 
         Ok(())
@@ -182,6 +421,36 @@ where
         Ok(0)
     }
 
+    /// Returns when a session expires within the database without loading its data.
+    ///
+    /// If client is None or the session does not exist it will return Ok(None).
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    /// - ['SessionError::GenericNotSupportedError'] is returned if the pool does not implement DatabasePool::expiry.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    /// use uuid::Uuid;
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config);
+    /// let token = Uuid::new_v4();
+    /// async {
+    ///     let expires = session_store.expiry_of(&token.to_string()).await.unwrap();
+    /// };
+    /// ```
+    ///
+    #[inline]
+    pub async fn expiry_of(&self, id: &str) -> Result<Option<DateTime<Utc>>, SessionError> {
+        if let Some(client) = &self.client {
+            return client.expiry(id, &self.config.table_name).await;
+        }
+
+        Ok(None)
+    }
+
     /// private internal function that loads a session's data from the database using a UUID string.
     ///
     /// If client is None it will return Ok(None).
@@ -211,9 +480,14 @@ where
             let result: Option<String> =
                 client.load(&cookie_value, &self.config.table_name).await?;
 
-            Ok(result
+            let session: Option<SessionData> = result
                 .map(|session| serde_json::from_str(&session))
-                .transpose()?)
+                .transpose()?;
+
+            Ok(session.map(|mut session| {
+                session.stored_last_accessed = session.last_accessed;
+                session
+            }))
         } else {
             Ok(None)
         }
@@ -257,8 +531,33 @@ where
         Ok(())
     }
 
+    /// private internal function that stores a session's data to the database
+    /// through the session's writer when single_writer is enabled.
+    pub(crate) async fn persist_session(&self, session: &SessionData) -> Result<(), SessionError> {
+        if self.config.single_writer {
+            self.write(
+                &session.id.to_string(),
+                SessionWrite::Store(Box::new(session.clone())),
+            )
+            .await
+        } else {
+            self.store_session(session).await
+        }
+    }
+
+    /// private internal function that deletes a session's data from the database
+    /// through the session's writer when single_writer is enabled.
+    pub(crate) async fn persist_destroy(&self, id: &str) -> Result<(), SessionError> {
+        if self.config.single_writer {
+            self.write(id, SessionWrite::Destroy).await
+        } else {
+            self.destroy_session(id).await
+        }
+    }
+
     /// Deletes a session's data from the database by its UUID.
     ///
+    /// If soft delete is enabled the session is only marked as deleted.
     /// If client is None it will return Ok(()).
     ///
     /// # Errors
@@ -281,7 +580,13 @@ where
     #[inline]
     pub async fn destroy_session(&self, id: &str) -> Result<(), SessionError> {
         if let Some(client) = &self.client {
-            client.delete_one_by_id(id, &self.config.table_name).await?;
+            if self.config.soft_delete {
+                client
+                    .soft_delete_one_by_id(id, &self.config.table_name)
+                    .await?;
+            } else {
+                client.delete_one_by_id(id, &self.config.table_name).await?;
+            }
         }
 
         Ok(())
@@ -348,6 +653,7 @@ where
             }
 
             inner.autoremove = Utc::now() + self.config.memory_lifespan;
+            inner.last_accessed = Utc::now();
             return true;
         }
 
@@ -381,6 +687,15 @@ where
         }
     }
 
+    #[inline]
+    pub(crate) fn persist(&self, id: String) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.persist = true;
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
     #[inline]
     pub(crate) fn set_store(&self, id: String, storable: bool) {
         if let Some(mut instance) = self.inner.get_mut(&id) {
@@ -390,6 +705,13 @@ where
         }
     }
 
+    #[inline]
+    pub(crate) fn view(&self, id: String) -> Option<SessionDataView> {
+        self.inner
+            .get(&id)
+            .map(|instance| SessionDataView::from(&*instance))
+    }
+
     #[inline]
     pub(crate) fn get<N: serde::de::DeserializeOwned>(&self, id: String, key: &str) -> Option<N> {
         if let Some(instance) = self.inner.get_mut(&id) {
@@ -417,7 +739,7 @@ where
     #[inline]
     pub(crate) fn set(&self, id: String, key: &str, value: impl Serialize) {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.set(key, value);
+            instance.set(key, value, &self.config);
         } else {
             tracing::warn!("Session data unexpectedly missing");
         }
@@ -441,6 +763,38 @@ where
         }
     }
 
+    #[inline]
+    pub(crate) fn csrf_token(&self, id: String) -> Option<String> {
+        self.inner
+            .get(&id)
+            .and_then(|instance| instance.csrf_token.clone())
+    }
+
+    #[inline]
+    pub(crate) fn verify_double_submit(&self, id: String, value: &str) -> bool {
+        self.inner
+            .get(&id)
+            .is_some_and(|instance| instance.verify_double_submit(value))
+    }
+
+    #[inline]
+    pub(crate) fn pin(&self, id: String, key: &str) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.pin(key);
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    #[inline]
+    pub(crate) fn unpin(&self, id: String, key: &str) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.unpin(key);
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
     #[inline]
     pub(crate) async fn count_sessions(&self) -> i64 {
         if self.is_persistent() {
@@ -450,3 +804,11 @@ where
         }
     }
 }
+
+fn enabled(is_set: bool) -> &'static str {
+    if is_set {
+        "enabled"
+    } else {
+        "disabled"
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{DatabasePool, SessionData, SessionError, SessionStore};
+use std::{fmt::Debug, time::Duration};
+use tokio::sync::{mpsc, oneshot};
+
+/// How long a Session's writer waits for more writes before it shuts down.
+const WRITER_IDLE: Duration = Duration::from_secs(30);
+
+/// The database writes a Session's writer can run.
+#[derive(Debug)]
+pub(crate) enum SessionWrite {
+    /// Stores the Session's data as it was when the write was sent,
+    /// so it is saved even if the Session has since been unloaded from memory.
+    Store(Box<SessionData>),
+    /// Deletes the Session from the database.
+    Destroy,
+}
+
+#[derive(Debug)]
+pub(crate) struct SessionWriteRequest {
+    write: SessionWrite,
+    done: oneshot::Sender<Result<(), SessionError>>,
+}
+
+pub(crate) type SessionWriter = mpsc::UnboundedSender<SessionWriteRequest>;
+
+impl<T> SessionStore<T>
+where
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Sends a write to the Session's writer, starting one if needed, and waits for it to finish.
+    pub(crate) async fn write(&self, id: &str, write: SessionWrite) -> Result<(), SessionError> {
+        let (done, wait) = oneshot::channel();
+        let mut request = SessionWriteRequest { write, done };
+
+        loop {
+            let writer = self
+                .writers
+                .entry(id.to_owned())
+                .or_insert_with(|| self.spawn_writer(id.to_owned()))
+                .clone();
+
+            match writer.send(request) {
+                Ok(()) => break,
+                Err(mpsc::error::SendError(returned)) => {
+                    // The writer shut down after being idle so lets replace it.
+                    self.writers
+                        .remove_if(id, |_, current| current.same_channel(&writer));
+                    request = returned;
+                }
+            }
+        }
+
+        wait.await.unwrap_or_else(|_| {
+            Err(SessionError::GenericInsertError(
+                "session writer stopped before finishing".to_owned(),
+            ))
+        })
+    }
+
+    fn spawn_writer(&self, id: String) -> SessionWriter {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<SessionWriteRequest>();
+        let store = self.clone();
+        let writer = sender.clone();
+
+        tokio::spawn(async move {
+            while let Ok(Some(request)) = tokio::time::timeout(WRITER_IDLE, receiver.recv()).await {
+                let mut requests = vec![request];
+
+                while let Ok(request) = receiver.try_recv() {
+                    requests.push(request);
+                }
+
+                store.run_writes(&id, requests).await;
+            }
+
+            // Stop taking new writes then finish anything that was sent before we closed.
+            store
+                .writers
+                .remove_if(&id, |_, current| current.same_channel(&writer));
+            receiver.close();
+
+            let mut requests = Vec::new();
+
+            while let Ok(request) = receiver.try_recv() {
+                requests.push(request);
+            }
+
+            if !requests.is_empty() {
+                store.run_writes(&id, requests).await;
+            }
+        });
+
+        sender
+    }
+
+    /// Runs only the last of the queued writes as each one replaces what the others would have saved.
+    ///
+    /// The last write's caller gets the original error. As errors can not be cloned the others get
+    /// the same message within a GenericInsertError.
+    async fn run_writes(&self, id: &str, mut requests: Vec<SessionWriteRequest>) {
+        let result = match requests.last().map(|request| &request.write) {
+            Some(SessionWrite::Store(session)) => self.store_session(session).await,
+            Some(SessionWrite::Destroy) => self.destroy_session(id).await,
+            None => Ok(()),
+        };
+
+        let error = result.as_ref().err().map(|err| err.to_string());
+
+        if let Some(error) = &error {
+            tracing::error!("Session writer failed to save {}: {}", id, error);
+        }
+
+        let last = requests.pop();
+
+        for request in requests {
+            let _ = request.done.send(match &error {
+                Some(error) => Err(SessionError::GenericInsertError(error.clone())),
+                None => Ok(()),
+            });
+        }
+
+        if let Some(last) = last {
+            let _ = last.done.send(result);
+        }
+    }
+}
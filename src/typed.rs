@@ -0,0 +1,127 @@
+use crate::{DatabasePool, Session, SessionRejection};
+use async_trait::async_trait;
+use axum_core::{
+    extract::FromRequestParts,
+    response::{IntoResponseParts, ResponseParts},
+};
+use http::request::Parts;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    convert::Infallible,
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+};
+
+/// The reserved Key a TypedSession's data is stored under.
+pub(crate) const TYPED_SESSION_KEY: &str = "__typed_session";
+
+/// A strongly typed view of the whole Session.
+///
+/// On extraction T is loaded from the Session, or set to T::default() when nothing is stored yet.
+/// If the stored data can not be deserialized into T, such as after T's fields changed,
+/// the extraction is rejected so the stored data is never replaced by the default.
+/// Changes are written back when the TypedSession is returned as part of the handler's response.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{SessionPgPool, TypedSession};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Default, Serialize, Deserialize)]
+/// struct AppSession {
+///     visits: u64,
+///     user_id: Option<i64>,
+/// }
+///
+/// async fn handler(mut session: TypedSession<AppSession, SessionPgPool>) -> (TypedSession<AppSession, SessionPgPool>, String) {
+///     session.visits += 1;
+///     let body = format!("visits {}", session.visits);
+///     (session, body)
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct TypedSession<T, P>
+where
+    P: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    data: T,
+    session: Session<P>,
+}
+
+impl<T, P> TypedSession<T, P>
+where
+    P: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Gets the untyped Session this was loaded from.
+    #[inline]
+    pub fn session(&self) -> &Session<P> {
+        &self.session
+    }
+
+    /// Returns the typed data without writing it back to the Session.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T, P> Deref for TypedSession<T, P>
+where
+    P: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<T, P> DerefMut for TypedSession<T, P>
+where
+    P: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+/// Adds `FromRequestParts<B>` for TypedSession
+///
+/// Loads T from the Session within Axums request extensions state.
+#[async_trait]
+impl<T, P, S> FromRequestParts<S> for TypedSession<T, P>
+where
+    T: Serialize + DeserializeOwned + Default + Send,
+    P: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    S: Send + Sync,
+{
+    type Rejection = SessionRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session = Session::<P>::from_request_parts(parts, state).await?;
+        let data = match session.get::<serde_json::Value>(TYPED_SESSION_KEY) {
+            Some(value) => serde_json::from_value(value).map_err(|err| {
+                tracing::error!("TypedSession failed to deserialize its data: {}", err);
+                SessionRejection::invalid_typed_session(parts)
+            })?,
+            None => T::default(),
+        };
+
+        Ok(TypedSession { data, session })
+    }
+}
+
+/// Writes T back into the Session when returned within a response.
+impl<T, P> IntoResponseParts for TypedSession<T, P>
+where
+    T: Serialize,
+    P: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    type Error = Infallible;
+
+    fn into_response_parts(self, res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        self.session.set(TYPED_SESSION_KEY, &self.data);
+        Ok(res)
+    }
+}
@@ -0,0 +1,88 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// The Trait used to store a type, generally an enum, so it can still be read after it changes between deploys.
+///
+/// Values are stored along with VERSION. When a stored value has a different version, or no longer
+/// deserializes because a variant was renamed or removed, `migrate` is given the chance to convert it
+/// and `fallback` is used if it can not. This way a stale session gets a default rather than losing the value.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::SessionVersioned;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// enum Theme {
+///     Light,
+///     Dark,
+/// }
+///
+/// impl SessionVersioned for Theme {
+///     const VERSION: u32 = 2;
+///
+///     fn fallback() -> Self {
+///         Theme::Light
+///     }
+///
+///     fn migrate(version: u32, value: serde_json::Value) -> Option<Self> {
+///         // Version 1 called Dark Night.
+///         match (version, value.as_str()) {
+///             (1, Some("Night")) => Some(Theme::Dark),
+///             _ => serde_json::from_value(value).ok(),
+///         }
+///     }
+/// }
+///
+/// session.set_versioned("theme", Theme::Dark);
+/// let theme: Option<Theme> = session.get_versioned("theme");
+/// ```
+///
+pub trait SessionVersioned: Serialize + DeserializeOwned {
+    /// The current version stored with the value. Increase it when the type changes.
+    const VERSION: u32;
+
+    /// The value used when a stored value can not be read or migrated.
+    fn fallback() -> Self;
+
+    /// Converts a value stored with a different version, or one that failed to deserialize.
+    /// Values stored by `Session::set` without a version are given version 0.
+    /// By default this tries to deserialize the value as is.
+    fn migrate(version: u32, value: Value) -> Option<Self> {
+        let _ = version;
+        serde_json::from_value(value).ok()
+    }
+}
+
+/// How a versioned value is stored within the Session.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct VersionedValue<T> {
+    version: u32,
+    value: T,
+}
+
+impl<T: SessionVersioned> VersionedValue<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self {
+            version: T::VERSION,
+            value,
+        }
+    }
+
+    /// Reads a stored value, migrating it or using the fallback when needed.
+    pub(crate) fn decode(stored: Value) -> T {
+        let (version, value) = match serde_json::from_value::<VersionedValue<Value>>(stored.clone())
+        {
+            Ok(versioned) => (versioned.version, versioned.value),
+            Err(_) => (0, stored),
+        };
+
+        if version == T::VERSION {
+            if let Ok(value) = serde_json::from_value(value.clone()) {
+                return value;
+            }
+        }
+
+        T::migrate(version, value).unwrap_or_else(T::fallback)
+    }
+}
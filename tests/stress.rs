@@ -0,0 +1,147 @@
+//! Stress tests for many concurrent sessions within the memory store.
+//!
+//! The number of sessions defaults to 2,000 so these run quickly within `cargo test`
+//! and can be raised with the `SESSION_STRESS_COUNT` environment variable, e.g.
+//! `SESSION_STRESS_COUNT=100000 cargo test --release --test stress`.
+
+use axum::{
+    body::Body,
+    extract::Query,
+    http::{header, HeaderValue, Request},
+    routing::get,
+    Router,
+};
+use axum_session::{Session, SessionConfig, SessionLayer, SessionNullPool, SessionStore};
+use chrono::Duration;
+use serde::Deserialize;
+use tower::ServiceExt;
+
+#[derive(Deserialize)]
+struct Value {
+    value: u64,
+}
+
+fn session_count() -> u64 {
+    std::env::var("SESSION_STRESS_COUNT")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(2_000)
+}
+
+/// How many waves concurrent_sessions_are_unloaded creates its sessions in, one memory lifetime apart.
+const WAVES: u64 = 5;
+
+fn app(config: SessionConfig) -> Router {
+    let session_store = SessionStore::<SessionNullPool>::new(None, config);
+
+    Router::new()
+        .route(
+            "/set",
+            get(
+                |session: Session<SessionNullPool>, Query(query): Query<Value>| async move {
+                    session.set("value", query.value);
+                },
+            ),
+        )
+        .route(
+            "/get",
+            get(|session: Session<SessionNullPool>| async move {
+                session.get::<u64>("value").unwrap_or(u64::MAX).to_string()
+            }),
+        )
+        .route(
+            "/count",
+            get(|session: Session<SessionNullPool>| async move { session.count().await.to_string() }),
+        )
+        .layer(SessionLayer::new(session_store))
+}
+
+async fn call(app: Router, uri: &str, cookies: &[HeaderValue]) -> (Vec<HeaderValue>, String) {
+    let mut request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+    for cookie in cookies {
+        request.headers_mut().append(header::COOKIE, cookie.clone());
+    }
+
+    let response = app.oneshot(request).await.unwrap();
+    let cookies = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .cloned()
+        .collect();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    (cookies, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_sessions() {
+    let count = session_count();
+    let app = app(SessionConfig::default());
+
+    let tasks = (0..count).map(|value| {
+        let app = app.clone();
+
+        tokio::spawn(async move {
+            let (cookies, _) = call(app.clone(), &format!("/set?value={}", value), &[]).await;
+            let (_, body) = call(app, "/get", &cookies).await;
+            assert_eq!(body, value.to_string(), "session returned another's data");
+            cookies
+        })
+    });
+
+    let mut cookies = Vec::new();
+
+    for task in futures::future::join_all(tasks).await {
+        cookies = task.unwrap();
+    }
+
+    let (_, body) = call(app, "/count", &cookies).await;
+    assert_eq!(body, count.to_string());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_sessions_are_unloaded() {
+    let batch = session_count() / WAVES;
+    let app = app(SessionConfig::default().with_memory_lifetime(Duration::seconds(1)));
+
+    // A session stays in memory for one lifetime after its last use and up to one more until
+    // the next sweep, so only the last three waves and the sessions made to count them are ever held.
+    let ceiling = 3 * batch + WAVES;
+    let mut most = 0;
+
+    for wave in 0..WAVES {
+        if wave > 0 {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+
+        let tasks = (0..batch).map(|value| {
+            let app = app.clone();
+
+            tokio::spawn(async move {
+                call(app, &format!("/set?value={}", value), &[]).await;
+            })
+        });
+
+        for task in futures::future::join_all(tasks).await {
+            task.unwrap();
+        }
+
+        let (_, body) = call(app.clone(), "/count", &[]).await;
+        most = most.max(body.parse::<u64>().unwrap());
+    }
+
+    assert!(
+        most <= ceiling,
+        "{} sessions were held in memory, more than the ceiling of {}",
+        most,
+        ceiling
+    );
+
+    // Once every session has outlived its memory lifetime the next request sweeps them,
+    // leaving only the session of the request that counts them.
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    let (_, body) = call(app, "/count", &[]).await;
+    assert_eq!(body, "1");
+}